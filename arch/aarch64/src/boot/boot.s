@@ -8,11 +8,6 @@
 .endm
 
 1:
-    // Core check: only boot if in EL2
-    mrs     x0,     CurrentEL
-    cmp     x0,     {CONST_CURRENTEL_EL2}
-    b.ne    9f      // v  Loop
-
     // Core check: park if on non-boot core
     mrs     x1,     MPIDR_EL1
     and     x1, x1, {CONST_CORE_ID_MASK}
@@ -1,8 +1,30 @@
-use core::{arch::naked_asm, cell::UnsafeCell};
+use core::{
+    arch::{asm, naked_asm},
+    cell::UnsafeCell,
+    ops::Range,
+};
 
 use aarch64_cpu::{asm, registers::*};
+use lib_kernel::Arch;
 
-use crate::{Aarch64, Aarch64Config};
+use crate::{core_block::CoreBlock, Aarch64, Aarch64Config, CORE_ID_MASK, MAX_CORES};
+
+/// Size of the stack for each secondary core.
+const SECONDARY_CORE_STACK_SIZE: usize = 64 * 1024;
+
+/// Bits [5:4] of `SCR_EL3` are reserved, and must be written as ones.
+const SCR_EL3_RES1: u64 = 0b11 << 4;
+
+/// Stacks for each secondary core, indexed by core ID. The slot for the boot core is unused, as it
+/// uses the stack provided by the linker.
+#[repr(C, align(16))]
+struct SecondaryCoreStacks(UnsafeCell<[[u8; SECONDARY_CORE_STACK_SIZE]; MAX_CORES]>);
+
+// Safety: Each stack is only ever used by the core it belongs to.
+unsafe impl Sync for SecondaryCoreStacks {}
+
+static SECONDARY_CORE_STACKS: SecondaryCoreStacks =
+    SecondaryCoreStacks(UnsafeCell::new([[0; SECONDARY_CORE_STACK_SIZE]; MAX_CORES]));
 
 #[allow(no_mangle_generic_items)]
 impl<Config: Aarch64Config> Aarch64<Config> {
@@ -20,25 +42,110 @@ impl<Config: Aarch64Config> Aarch64<Config> {
     pub(crate) unsafe extern "C" fn _start() -> ! {
         naked_asm!(
             include_str!("boot.s"),
-            CONST_CURRENTEL_EL2 = const 0x8,
-            CONST_CORE_ID_MASK = const 0b11,
+            CONST_CORE_ID_MASK = const CORE_ID_MASK,
             CONST_BOOT_CORE_ID = const Self::BOOT_CORE_ID,
         )
     }
 
+    /// Start procedure for secondary cores, once released by the boot core.
+    ///
+    /// # Safety
+    ///
+    /// Must only be jumped to by a secondary core as it begins execution, after the boot core has
+    /// initialised memory.
+    #[naked]
+    pub(crate) unsafe extern "C" fn _start_secondary() -> ! {
+        naked_asm!(
+            include_str!("secondary.s"),
+            CONST_CORE_ID_MASK = const CORE_ID_MASK,
+            CONST_STACK_SIZE = const SECONDARY_CORE_STACK_SIZE,
+            SECONDARY_CORE_STACKS = sym SECONDARY_CORE_STACKS,
+            START_RUST = sym Self::_start_rust,
+        )
+    }
+
     /// Entry point for Rust.
     ///
+    /// The core may be in EL3, EL2 or EL1, and will be brought down to EL1 before entering the
+    /// kernel. The boot core will enter [`Aarch64Config::KERNEL_MAIN`], whilst all other cores will
+    /// enter [`Aarch64Config::SECONDARY_MAIN`].
+    ///
     /// # Safety
     ///
     /// Requires memory, including the stack, to be correctly configured.
     #[no_mangle]
     pub(crate) unsafe extern "C" fn _start_rust() -> ! {
+        // Everything relies on knowing which core it's running on, so this must happen first
+        CoreBlock::install((MPIDR_EL1.get() & CORE_ID_MASK) as usize);
+        CoreBlock::current().set_stack(Self::stack());
+
+        match CurrentEL.read_as_enum(CurrentEL::EL) {
+            Some(CurrentEL::EL::Value::EL3) => Self::el3_to_el2(),
+            Some(CurrentEL::EL::Value::EL2) => Self::el2_to_el1(),
+            Some(CurrentEL::EL::Value::EL1) => Self::start_el1(),
+            // Booting in EL0 is not possible, so something has gone very wrong
+            _ => loop {
+                asm::wfe();
+            },
+        }
+    }
+
+    /// Drop from EL3 into EL2, continuing the boot process in [`Self::el2_to_el1`].
+    ///
+    /// # Safety
+    ///
+    /// Must only be called whilst in EL3 during boot.
+    unsafe extern "C" fn el3_to_el2() -> ! {
+        // The counter frequency is only writable from the highest EL, so firmware which enters
+        // at EL3 may have left it unset for the kernel to program
+        asm!(
+            "msr CNTFRQ_EL0, {}",
+            in(reg) Config::COUNTER_FREQUENCY as u64,
+            options(nostack),
+        );
+
+        // Lower ELs are non-secure and Aarch64, with hypervisor calls available. There
+        // is no secure monitor, so secure monitor calls are disabled.
+        let fields = SCR_EL3::NS::NonSecure
+            + SCR_EL3::RW::NextELIsAarch64
+            + SCR_EL3::HCE::HvcEnabled
+            + SCR_EL3::SMD::SmcDisabled;
+        SCR_EL3.set(SCR_EL3_RES1 | fields.value);
+
+        // C5-800: Fake an exception return to enter EL2
+        SPSR_EL3.write(
+            SPSR_EL3::D::Masked
+                + SPSR_EL3::A::Masked
+                + SPSR_EL3::I::Masked
+                + SPSR_EL3::F::Masked
+                + SPSR_EL3::M::EL2h,
+        );
+
+        // Continue booting from EL2
+        ELR_EL3.set(Self::el2_to_el1 as *const () as u64);
+
+        // Set up the EL2 stack to re-use the existing stack
+        asm!("msr SP_EL2, {}", in(reg) Self::stack_end(), options(nostack));
+
+        // Perform the exception return
+        asm::eret()
+    }
+
+    /// Drop from EL2 into EL1, entering the kernel.
+    ///
+    /// # Safety
+    ///
+    /// Must only be called whilst in EL2 during boot.
+    unsafe extern "C" fn el2_to_el1() -> ! {
         // D19-6632: Configure hypervisor controller to enable aarch64 in EL1
         HCR_EL2.write(HCR_EL2::RW::EL1IsAarch64);
 
         // Set up timer access for EL1
         Self::enable_el1_timers();
 
+        // Exceptions will be taken to EL1, so ensure they can be handled
+        Self::install_exception_vectors();
+
         // C5-800: Fake an exception return to enter EL1
         SPSR_EL2.write(
             SPSR_EL2::D::Masked
@@ -49,17 +156,66 @@ impl<Config: Aarch64Config> Aarch64<Config> {
         );
 
         // Set the link address to return from the exception
-        ELR_EL2.set(Config::KERNEL_MAIN as *const () as u64);
+        ELR_EL2.set(Self::entry() as *const () as u64);
+
+        // Set up the EL1 stack to re-use the existing stack
+        SP_EL1.set(Self::stack_end());
 
+        // Perform the exception return
+        asm::eret()
+    }
+
+    /// Enter the kernel directly, as the core was started in EL1.
+    ///
+    /// # Safety
+    ///
+    /// Must only be called whilst in EL1 during boot. Any hypervisor must have already granted
+    /// access to the EL1 timers.
+    unsafe fn start_el1() -> ! {
+        // Match the state that the other ELs would have left the core in
+        DAIF.write(DAIF::D::Masked + DAIF::A::Masked + DAIF::I::Masked + DAIF::F::Masked);
+
+        Self::install_exception_vectors();
+
+        (Self::entry())()
+    }
+
+    /// Kernel entry point for the current core.
+    fn entry() -> fn() -> ! {
+        if <Self as Arch>::core_id() == Self::BOOT_CORE_ID {
+            Config::KERNEL_MAIN
+        } else {
+            Config::SECONDARY_MAIN
+        }
+    }
+
+    /// Address of the top of the current core's stack.
+    fn stack_end() -> u64 {
+        Self::stack().end as u64
+    }
+
+    /// Addresses of the current core's boot stack.
+    fn stack() -> Range<usize> {
         extern "C" {
+            static __boot_core_stack_start: UnsafeCell<()>;
             static __boot_core_stack_end_exclusive: UnsafeCell<()>;
         }
 
-        // Set up the EL1 stack to re-use the existing stack
-        SP_EL1.set(__boot_core_stack_end_exclusive.get() as u64);
+        let core = <Self as Arch>::core_id();
 
-        // Perform the exception return
-        asm::eret()
+        if core == Self::BOOT_CORE_ID {
+            // Safety: Only the addresses of the symbols are taken, which are provided by the
+            // linker.
+            unsafe {
+                __boot_core_stack_start.get() as usize
+                    ..__boot_core_stack_end_exclusive.get() as usize
+            }
+        } else {
+            let start = SECONDARY_CORE_STACKS.0.get().cast::<u8>() as usize
+                + core * SECONDARY_CORE_STACK_SIZE;
+
+            start..start + SECONDARY_CORE_STACK_SIZE
+        }
     }
 
     /// Configure access to timers and counters in EL1.
@@ -0,0 +1,15 @@
+    // Find the ID of this core
+    mrs     x0,     MPIDR_EL1
+    and     x0, x0, {CONST_CORE_ID_MASK}
+
+    // Load the address of the secondary core stacks, relative to the PC
+    adrp    x1,     {SECONDARY_CORE_STACKS}
+    add     x1, x1, #:lo12:{SECONDARY_CORE_STACKS}
+
+    // Set up the stack pointer at the top of this core's stack
+    add     x0, x0, #1
+    mov     x2,     {CONST_STACK_SIZE}
+    madd    x1, x0, x2, x1
+    mov     sp, x1
+
+    b       {START_RUST}
@@ -0,0 +1,98 @@
+use core::{
+    ops::Range,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use aarch64_cpu::registers::*;
+
+use crate::MAX_CORES;
+
+/// State belonging to a single core, which is always available through `TPIDR_EL1`.
+#[repr(C)]
+pub(crate) struct CoreBlock {
+    /// ID of the core that this block belongs to.
+    pub id: usize,
+
+    /// Number of nested IRQ handlers that the core is currently running.
+    irq_depth: AtomicUsize,
+
+    /// Lowest address of the stack of the thread that the core is running.
+    stack_start: AtomicUsize,
+    /// Address just past the top of the stack of the thread that the core is running.
+    stack_end: AtomicUsize,
+}
+
+/// Blocks for every core, indexed by core ID.
+static CORE_BLOCKS: [CoreBlock; MAX_CORES] = {
+    let mut blocks = [const {
+        CoreBlock {
+            id: 0,
+            irq_depth: AtomicUsize::new(0),
+            stack_start: AtomicUsize::new(0),
+            stack_end: AtomicUsize::new(0),
+        }
+    }; MAX_CORES];
+
+    let mut i = 0;
+    while i < MAX_CORES {
+        blocks[i].id = i;
+        i += 1;
+    }
+
+    blocks
+};
+
+impl CoreBlock {
+    /// Point `TPIDR_EL1` at the block for `core`.
+    ///
+    /// # Safety
+    ///
+    /// Must be called on each core during boot, before [`CoreBlock::current`] is used, and `core`
+    /// must be the ID of the calling core.
+    pub unsafe fn install(core: usize) {
+        TPIDR_EL1.set(&CORE_BLOCKS[core] as *const CoreBlock as u64);
+    }
+
+    /// Record that the core has entered an IRQ handler.
+    pub fn enter_irq(&self) {
+        self.irq_depth.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record that the core has left an IRQ handler.
+    pub fn exit_irq(&self) {
+        self.irq_depth.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// Number of nested IRQ handlers that the core is currently running.
+    pub fn irq_depth(&self) -> usize {
+        self.irq_depth.load(Ordering::Relaxed)
+    }
+
+    /// Replace the IRQ nesting depth, such as when switching to a thread which was suspended
+    /// inside an IRQ handler.
+    pub fn set_irq_depth(&self, depth: usize) {
+        self.irq_depth.store(depth, Ordering::Relaxed);
+    }
+
+    /// Determine whether the core is running an IRQ handler.
+    pub fn in_irq(&self) -> bool {
+        self.irq_depth.load(Ordering::Relaxed) > 0
+    }
+
+    /// Addresses of the stack of the thread that the core is running.
+    pub fn stack(&self) -> Range<usize> {
+        self.stack_start.load(Ordering::Relaxed)..self.stack_end.load(Ordering::Relaxed)
+    }
+
+    /// Replace the stack of the thread that the core is running, such as when switching threads.
+    pub fn set_stack(&self, stack: Range<usize>) {
+        self.stack_start.store(stack.start, Ordering::Relaxed);
+        self.stack_end.store(stack.end, Ordering::Relaxed);
+    }
+
+    /// Block of the current core.
+    pub fn current() -> &'static CoreBlock {
+        // Safety: `TPIDR_EL1` is only ever set by `install` to point at a static block.
+        unsafe { &*(TPIDR_EL1.get() as *const CoreBlock) }
+    }
+}
@@ -0,0 +1,142 @@
+//! Busy-wait delays, for drivers which need to wait for short, precise lengths of time.
+//!
+//! Delays are measured with the system counter. As the counter can't measure less than a single
+//! tick, shorter delays (and all delays, if the firmware didn't set the counter frequency) instead
+//! spin for a number of loop iterations, calibrated against the counter by
+//! [`Aarch64::calibrate_delay`]. Any delay may be extended by interrupts, so interrupts must be
+//! masked if an upper bound matters.
+
+use core::{
+    arch::asm,
+    marker::PhantomData,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use crate::{Aarch64, Aarch64Config};
+
+const NANOS_PER_SEC: u64 = 1_000_000_000;
+
+/// Loop iterations per microsecond assumed until the delay loop is calibrated. This errs long, by
+/// assuming a core clock of 2GHz and a single cycle per iteration.
+const DEFAULT_LOOPS_PER_US: u64 = 2000;
+
+/// Number of loop iterations timed during calibration.
+const CALIBRATION_LOOPS: u64 = 1_000_000;
+
+/// Calibrated number of delay loop iterations per microsecond.
+static LOOPS_PER_US: AtomicU64 = AtomicU64::new(DEFAULT_LOOPS_PER_US);
+
+impl<C: Aarch64Config> Aarch64<C> {
+    /// Measure the speed of the delay loop against the system counter, so that short delays are
+    /// accurate. Has no effect if the frequency of the counter is unknown.
+    ///
+    /// Interrupts should be masked whilst this runs, otherwise the loop will be measured as slower
+    /// than it is, and delays will be too short.
+    pub fn calibrate_delay() {
+        let frequency = Self::counter_frequency();
+
+        if frequency == 0 {
+            return;
+        }
+
+        let start = Self::counter();
+        spin(CALIBRATION_LOOPS);
+        let ticks = Self::counter() - start;
+
+        let nanos = (ticks as u128 * NANOS_PER_SEC as u128 / frequency as u128).max(1) as u64;
+        let loops_per_us = (CALIBRATION_LOOPS * 1000 / nanos).max(1);
+
+        LOOPS_PER_US.store(loops_per_us, Ordering::Relaxed);
+    }
+
+    /// Wait for at least `duration`.
+    pub fn delay(duration: Duration) {
+        Self::delay_ns(duration.as_nanos().try_into().unwrap_or(u64::MAX));
+    }
+
+    /// Wait for at least `nanos` nanoseconds.
+    pub fn delay_ns(nanos: u64) {
+        let frequency = Self::counter_frequency();
+
+        // Rounded up, so the delay is never shorter than requested
+        let ticks = (nanos as u128 * frequency as u128).div_ceil(NANOS_PER_SEC as u128);
+
+        if ticks <= 1 {
+            let loops = nanos.saturating_mul(LOOPS_PER_US.load(Ordering::Relaxed));
+
+            spin(loops.div_ceil(1000));
+            return;
+        }
+
+        // The first tick may already be partly over, so an extra tick must pass
+        let deadline = Self::counter().saturating_add(ticks.min(u64::MAX as u128) as u64 + 1);
+
+        while Self::counter() < deadline {
+            core::hint::spin_loop();
+        }
+    }
+
+    /// Wait for at least `micros` microseconds.
+    pub fn delay_us(micros: u64) {
+        Self::delay_ns(micros.saturating_mul(1000));
+    }
+
+    /// Wait for at least `millis` milliseconds.
+    pub fn delay_ms(millis: u64) {
+        Self::delay_ns(millis.saturating_mul(1_000_000));
+    }
+}
+
+/// Run the delay loop for `loops` iterations.
+fn spin(loops: u64) {
+    if loops == 0 {
+        return;
+    }
+
+    // Safety: The loop only decrements its own register.
+    unsafe {
+        asm!(
+            "1:",
+            "subs {loops}, {loops}, #1",
+            "b.ne 1b",
+            loops = inout(reg) loops => _,
+            options(nomem, nostack),
+        )
+    };
+}
+
+/// Busy-wait delay provider for drivers written against `embedded-hal`.
+pub struct Delay<Config> {
+    // The configuration is only used at compile time, so never prevents sharing between cores
+    _config: PhantomData<fn() -> Config>,
+}
+
+impl<C: Aarch64Config> Delay<C> {
+    /// Create a new delay provider. Any number may exist at once.
+    pub const fn new() -> Self {
+        Self {
+            _config: PhantomData,
+        }
+    }
+}
+
+impl<C: Aarch64Config> Default for Delay<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C: Aarch64Config> embedded_hal::delay::DelayNs for Delay<C> {
+    fn delay_ns(&mut self, ns: u32) {
+        Aarch64::<C>::delay_ns(ns.into());
+    }
+
+    fn delay_us(&mut self, us: u32) {
+        Aarch64::<C>::delay_us(us.into());
+    }
+
+    fn delay_ms(&mut self, ms: u32) {
+        Aarch64::<C>::delay_ms(ms.into());
+    }
+}
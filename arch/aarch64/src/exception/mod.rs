@@ -0,0 +1,162 @@
+use core::{
+    arch::{asm, naked_asm},
+    fmt,
+    ops::Range,
+};
+
+use aarch64_cpu::{asm, registers::*};
+
+use crate::{core_block::CoreBlock, Aarch64, Aarch64Config};
+
+/// State of the interrupted code, saved to the stack by the exception vectors before a handler is
+/// called. Any modifications made to the frame will be restored when the handler returns.
+#[repr(C)]
+#[derive(Clone)]
+pub struct ExceptionFrame {
+    /// General purpose registers `x0` to `x29`.
+    pub gpr: [u64; 30],
+    /// Link register (`x30`).
+    pub lr: u64,
+    /// Address that the exception will return to.
+    pub elr: u64,
+    /// Saved process state of the interrupted code.
+    pub spsr: u64,
+    /// Syndrome of the exception.
+    pub esr: u64,
+}
+
+impl ExceptionFrame {
+    /// Fill `addresses` with the interrupted address, followed by the return address of each frame
+    /// record on `stack`, which must be the stack of the interrupted code. Returns the number of
+    /// addresses written.
+    ///
+    /// The frame pointer may be corrupt, so the walk stops at the first record which isn't
+    /// entirely within `stack`.
+    pub fn backtrace(&self, stack: Range<usize>, addresses: &mut [usize]) -> usize {
+        let Some((first, rest)) = addresses.split_first_mut() else {
+            return 0;
+        };
+
+        *first = self.elr as usize;
+        let mut count = 1;
+
+        // Each frame record is the previous frame pointer followed by the return address
+        let mut frame_pointer = self.gpr[29] as usize;
+
+        for address in rest {
+            let in_stack = frame_pointer >= stack.start
+                && frame_pointer
+                    .checked_add(16)
+                    .is_some_and(|end| end <= stack.end);
+
+            if frame_pointer == 0 || frame_pointer % 16 != 0 || !in_stack {
+                break;
+            }
+
+            // Safety: The frame pointer is non-null, aligned, and the record lies within the stack
+            // of the interrupted code, so it can be read.
+            let [previous, return_address] = unsafe { *(frame_pointer as *const [usize; 2]) };
+
+            if return_address == 0 {
+                break;
+            }
+
+            *address = return_address;
+            count += 1;
+
+            // Stacks grow down, so older records must be at higher addresses
+            if previous <= frame_pointer {
+                break;
+            }
+
+            frame_pointer = previous;
+        }
+
+        count
+    }
+}
+
+impl fmt::Debug for ExceptionFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "ESR: {:#018x}", self.esr)?;
+        writeln!(f, "ELR: {:#018x}", self.elr)?;
+        writeln!(f, "SPSR: {:#018x}", self.spsr)?;
+        writeln!(f, "FAR: {:#018x}", FAR_EL1.get())?;
+
+        for (i, pair) in self.gpr.chunks(2).enumerate() {
+            writeln!(
+                f,
+                "x{:<2}: {:#018x}  x{:<2}: {:#018x}",
+                i * 2,
+                pair[0],
+                i * 2 + 1,
+                pair[1]
+            )?;
+        }
+
+        write!(f, "lr : {:#018x}", self.lr)
+    }
+}
+
+impl<Config: Aarch64Config> Aarch64<Config> {
+    /// Exception vector table for EL1.
+    ///
+    /// # Safety
+    ///
+    /// Must never be called directly, only installed into `VBAR_EL1`. The table must be aligned to
+    /// 2kB.
+    #[naked]
+    #[repr(align(2048))]
+    unsafe extern "C" fn exception_vectors() {
+        naked_asm!(
+            include_str!("vectors.s"),
+            CURRENT_EL_SPX_SYNCHRONOUS = sym Self::current_el_spx_synchronous,
+            CURRENT_EL_SPX_IRQ = sym Self::current_el_spx_irq,
+            CURRENT_EL_SPX_FIQ = sym Self::current_el_spx_fiq,
+            UNHANDLED_EXCEPTION = sym Self::unhandled_exception,
+        )
+    }
+
+    /// Install the exception vectors for EL1.
+    ///
+    /// # Safety
+    ///
+    /// Must be called from EL1 or EL2, before any exceptions are expected to be taken to EL1.
+    pub(crate) unsafe fn install_exception_vectors() {
+        VBAR_EL1.set(Self::exception_vectors as *const () as u64);
+
+        // Ensure the vectors are in place before continuing
+        asm::barrier::isb(asm::barrier::SY);
+    }
+
+    /// Synchronous exception taken from EL1.
+    extern "C" fn current_el_spx_synchronous(frame: &mut ExceptionFrame) {
+        panic!("Unhandled synchronous exception\n{frame:?}");
+    }
+
+    /// IRQ taken from EL1, which is passed to the handler provided by the configuration.
+    extern "C" fn current_el_spx_irq(_frame: &mut ExceptionFrame) {
+        CoreBlock::current().enter_irq();
+
+        // Taking the exception masked FIQs as well as IRQs, but FIQs must remain deliverable as
+        // non-maskable interrupts, even to a core stuck in an IRQ handler.
+        // Safety: Clearing the `F` bit of `DAIF` only allows FIQs to be taken.
+        unsafe { asm!("msr DAIFClr, #0b0001", options(nostack)) };
+
+        (Config::IRQ_HANDLER)();
+
+        // The handler may have switched threads, so this may now be running on a different core
+        CoreBlock::current().exit_irq();
+    }
+
+    /// FIQ taken from EL1, which is passed to the non-maskable interrupt handler provided by the
+    /// configuration.
+    extern "C" fn current_el_spx_fiq(frame: &mut ExceptionFrame) {
+        (Config::NMI_HANDLER)(frame);
+    }
+
+    /// Any exception that the kernel does not expect to receive.
+    extern "C" fn unhandled_exception(frame: &mut ExceptionFrame) {
+        panic!("Unexpected exception\n{frame:?}");
+    }
+}
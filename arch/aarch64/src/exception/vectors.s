@@ -0,0 +1,94 @@
+// Save the state of the interrupted code to the stack, and call the handler with a pointer to the
+// saved `ExceptionFrame`. Each entry of the vector table is limited to 0x80 bytes (32
+// instructions), so restoring the state is shared between all entries.
+.macro CALL_WITH_CONTEXT handler
+.balign 0x80
+    // Make room for the `ExceptionFrame`
+    sub     sp, sp,     #16 * 17
+
+    // Save the general purpose registers
+    stp     x0, x1,     [sp, #16 * 0]
+    stp     x2, x3,     [sp, #16 * 1]
+    stp     x4, x5,     [sp, #16 * 2]
+    stp     x6, x7,     [sp, #16 * 3]
+    stp     x8, x9,     [sp, #16 * 4]
+    stp     x10, x11,   [sp, #16 * 5]
+    stp     x12, x13,   [sp, #16 * 6]
+    stp     x14, x15,   [sp, #16 * 7]
+    stp     x16, x17,   [sp, #16 * 8]
+    stp     x18, x19,   [sp, #16 * 9]
+    stp     x20, x21,   [sp, #16 * 10]
+    stp     x22, x23,   [sp, #16 * 11]
+    stp     x24, x25,   [sp, #16 * 12]
+    stp     x26, x27,   [sp, #16 * 13]
+    stp     x28, x29,   [sp, #16 * 14]
+
+    // Save the exception state
+    mrs     x1,         ELR_EL1
+    mrs     x2,         SPSR_EL1
+    mrs     x3,         ESR_EL1
+    stp     lr, x1,     [sp, #16 * 15]
+    stp     x2, x3,     [sp, #16 * 16]
+
+    // Pass the frame as the first argument to the handler
+    mov     x0,         sp
+    bl      \handler
+
+    b       9f      // v  Restore context
+.endm
+
+// Current EL with SP0
+CALL_WITH_CONTEXT {UNHANDLED_EXCEPTION}
+CALL_WITH_CONTEXT {UNHANDLED_EXCEPTION}
+CALL_WITH_CONTEXT {UNHANDLED_EXCEPTION}
+CALL_WITH_CONTEXT {UNHANDLED_EXCEPTION}
+
+// Current EL with SPx
+CALL_WITH_CONTEXT {CURRENT_EL_SPX_SYNCHRONOUS}
+CALL_WITH_CONTEXT {CURRENT_EL_SPX_IRQ}
+CALL_WITH_CONTEXT {CURRENT_EL_SPX_FIQ}
+CALL_WITH_CONTEXT {UNHANDLED_EXCEPTION}
+
+// Lower EL using Aarch64
+CALL_WITH_CONTEXT {UNHANDLED_EXCEPTION}
+CALL_WITH_CONTEXT {UNHANDLED_EXCEPTION}
+CALL_WITH_CONTEXT {UNHANDLED_EXCEPTION}
+CALL_WITH_CONTEXT {UNHANDLED_EXCEPTION}
+
+// Lower EL using Aarch32
+CALL_WITH_CONTEXT {UNHANDLED_EXCEPTION}
+CALL_WITH_CONTEXT {UNHANDLED_EXCEPTION}
+CALL_WITH_CONTEXT {UNHANDLED_EXCEPTION}
+CALL_WITH_CONTEXT {UNHANDLED_EXCEPTION}
+
+9:                  // <- Restore context
+    // The handler may have unmasked interrupts, and one taken now would overwrite the exception
+    // state as it is restored
+    msr     DAIFSet,    #0b0011
+
+    // Restore the exception state, which the handler may have modified
+    ldr     x19,        [sp, #16 * 16]
+    ldp     lr, x20,    [sp, #16 * 15]
+    msr     SPSR_EL1,   x19
+    msr     ELR_EL1,    x20
+
+    // Restore the general purpose registers
+    ldp     x0, x1,     [sp, #16 * 0]
+    ldp     x2, x3,     [sp, #16 * 1]
+    ldp     x4, x5,     [sp, #16 * 2]
+    ldp     x6, x7,     [sp, #16 * 3]
+    ldp     x8, x9,     [sp, #16 * 4]
+    ldp     x10, x11,   [sp, #16 * 5]
+    ldp     x12, x13,   [sp, #16 * 6]
+    ldp     x14, x15,   [sp, #16 * 7]
+    ldp     x16, x17,   [sp, #16 * 8]
+    ldp     x18, x19,   [sp, #16 * 9]
+    ldp     x20, x21,   [sp, #16 * 10]
+    ldp     x22, x23,   [sp, #16 * 11]
+    ldp     x24, x25,   [sp, #16 * 12]
+    ldp     x26, x27,   [sp, #16 * 13]
+    ldp     x28, x29,   [sp, #16 * 14]
+
+    add     sp, sp,     #16 * 17
+
+    eret
@@ -1,13 +1,36 @@
 #![no_std]
+#![feature(fn_align)]
 #![feature(naked_functions)]
 
 mod boot;
+mod core_block;
+mod delay;
+mod exception;
+mod smp;
+mod thread;
 mod time;
 
-use core::marker::PhantomData;
+use core::{arch::asm, marker::PhantomData, ops::Range};
 
+use aarch64_cpu::{
+    asm::{self, wfe, wfi},
+    registers::*,
+};
+use core_block::CoreBlock;
 use lib_kernel::Arch;
 
+pub use delay::Delay;
+pub use exception::ExceptionFrame;
+pub use smp::{PsciConduit, StartMethod};
+pub use thread::ThreadContext;
+pub use time::GenericTimer;
+
+/// Mask to extract the core ID from `MPIDR_EL1`.
+const CORE_ID_MASK: u64 = 0b11;
+
+/// Maximum number of cores, as limited by [`CORE_ID_MASK`].
+const MAX_CORES: usize = CORE_ID_MASK as usize + 1;
+
 /// Configuration that a BSP must provide if it relies on the Aarch64 architecture.
 pub trait Aarch64Config {
     /// ID of the boot core.
@@ -15,6 +38,20 @@ pub trait Aarch64Config {
 
     /// Entry point for the kernel to be called once the device has booted.
     const KERNEL_MAIN: fn() -> !;
+
+    /// Entry point for the kernel to be called on each secondary core, once it has been started.
+    const SECONDARY_MAIN: fn() -> !;
+
+    /// Handler to be called whenever an IRQ is taken.
+    const IRQ_HANDLER: fn();
+
+    /// Handler to be called whenever an FIQ is taken, which is used as a non-maskable interrupt.
+    /// It is given the state of the interrupted code.
+    const NMI_HANDLER: fn(&ExceptionFrame);
+
+    /// Frequency of the system counter in Hz. This is only programmed when booting in EL3, as
+    /// `CNTFRQ_EL0` can only be written from there, and is otherwise left to the firmware.
+    const COUNTER_FREQUENCY: u32;
 }
 
 /// Core structure to contain all state of this architecture.
@@ -27,6 +64,130 @@ pub struct Aarch64<Config> {
 
 impl<C: Aarch64Config> Arch for Aarch64<C> {
     const LINKER_FUNCTIONS: &[unsafe extern "C" fn() -> !] = &[_start, Self::_start_rust];
+
+    const MAX_CORES: usize = MAX_CORES;
+
+    type ExceptionFrame = ExceptionFrame;
+
+    type ThreadContext = ThreadContext;
+
+    const EMPTY_THREAD_CONTEXT: ThreadContext = ThreadContext::EMPTY;
+
+    fn core_id() -> usize {
+        CoreBlock::current().id
+    }
+
+    unsafe fn enable_interrupts() {
+        // C5.2.3: Clear the `I` and `F` bits of `DAIF`. FIQs are used as non-maskable interrupts,
+        // so are never masked again once enabled.
+        asm!("msr DAIFClr, #0b0011", options(nostack));
+    }
+
+    fn disable_interrupts() {
+        // C5.2.2: Set the `I` bit of `DAIF`.
+        unsafe { asm!("msr DAIFSet, #0b0010", options(nostack)) };
+    }
+
+    fn interrupts_enabled() -> bool {
+        DAIF.matches_all(DAIF::I::Unmasked)
+    }
+
+    fn in_interrupt() -> bool {
+        CoreBlock::current().in_irq()
+    }
+
+    fn interrupt_depth() -> usize {
+        CoreBlock::current().irq_depth()
+    }
+
+    unsafe fn set_interrupt_depth(depth: usize) {
+        CoreBlock::current().set_irq_depth(depth);
+    }
+
+    fn wait_for_interrupt() {
+        wfi();
+    }
+
+    fn wait_for_event() {
+        wfe();
+    }
+
+    fn send_event() {
+        // Ensure prior writes are visible before waiting cores are woken
+        asm::barrier::dsb(asm::barrier::ISHST);
+        asm::sev();
+    }
+
+    fn counter() -> u64 {
+        Self::counter()
+    }
+
+    fn counter_frequency() -> u64 {
+        Self::counter_frequency()
+    }
+
+    fn new_thread_context(
+        stack: Range<usize>,
+        entry: extern "C" fn(usize) -> !,
+        arg: usize,
+    ) -> ThreadContext {
+        ThreadContext::new(stack, entry, arg)
+    }
+
+    unsafe fn switch_thread(from: *mut ThreadContext, to: *const ThreadContext) {
+        thread::switch(from, to);
+    }
+
+    fn backtrace(frame: &ExceptionFrame, addresses: &mut [usize]) -> usize {
+        // Exceptions are taken on the stack of the interrupted thread
+        frame.backtrace(CoreBlock::current().stack(), addresses)
+    }
+
+    fn invalidate_tlb() {
+        // Ensure any table updates are visible before invalidating, and that the invalidation is
+        // complete before continuing.
+        unsafe {
+            asm!(
+                "dsb ishst",
+                "tlbi vmalle1",
+                "dsb ish",
+                "isb",
+                options(nostack)
+            )
+        };
+    }
+
+    fn clean_dcache(start: usize, len: usize) {
+        for line in dcache_lines(start, len) {
+            // Clean by virtual address to the point of coherency
+            unsafe { asm!("dc cvac, {}", in(reg) line, options(nostack)) };
+        }
+
+        // Ensure the data has reached memory before any device is told to read it
+        asm::barrier::dsb(asm::barrier::SY);
+    }
+
+    fn invalidate_dcache(start: usize, len: usize) {
+        for line in dcache_lines(start, len) {
+            // Clean and invalidate rather than only invalidate, as a partial line may hold other
+            // data which hasn't been written back.
+            unsafe { asm!("dc civac, {}", in(reg) line, options(nostack)) };
+        }
+
+        asm::barrier::dsb(asm::barrier::SY);
+    }
+}
+
+/// Address of each data cache line which covers `start..start + len`.
+fn dcache_lines(start: usize, len: usize) -> impl Iterator<Item = usize> {
+    let ctr: u64;
+
+    // `CTR_EL0.DminLine` is the log2 of the number of words in the smallest data cache line of
+    // any cache.
+    unsafe { asm!("mrs {}, ctr_el0", out(reg) ctr, options(nomem, nostack)) };
+    let line_size = 4 << ((ctr >> 16) & 0b1111);
+
+    (start & !(line_size - 1)..start + len).step_by(line_size)
 }
 
 #[no_mangle]
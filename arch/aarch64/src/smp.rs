@@ -0,0 +1,88 @@
+use core::arch::asm;
+
+use aarch64_cpu::asm::{
+    barrier::{dsb, SY},
+    sev,
+};
+
+use crate::{Aarch64, Aarch64Config};
+
+/// Mechanism used by firmware to hold secondary cores until they are started.
+#[derive(Clone, Copy, Debug)]
+pub enum StartMethod {
+    /// The core is polling `release_address`, and will jump to the address written there once
+    /// woken by an event.
+    SpinTable { release_address: usize },
+
+    /// The core is held by firmware implementing the Power State Coordination Interface, which
+    /// can be called with the provided conduit.
+    Psci(PsciConduit),
+}
+
+/// Instruction used to call into PSCI firmware.
+#[derive(Clone, Copy, Debug)]
+pub enum PsciConduit {
+    /// Firmware is running in EL2, and is called with `hvc`.
+    Hvc,
+    /// Firmware is running in EL3, and is called with `smc`.
+    Smc,
+}
+
+/// Function ID of `CPU_ON` using the SMC64 calling convention.
+const PSCI_CPU_ON: u64 = 0xC400_0003;
+
+impl<Config: Aarch64Config> Aarch64<Config> {
+    /// Start a secondary core, which will enter [`Aarch64Config::SECONDARY_MAIN`] once it has
+    /// booted. Returns the PSCI error code if the firmware refuses to start the core.
+    ///
+    /// # Safety
+    ///
+    /// `core` must be held by firmware using `method`, and must not have already been started.
+    pub unsafe fn start_core(core: usize, method: StartMethod) -> Result<(), i64> {
+        let entry = Self::_start_secondary as *const () as u64;
+
+        match method {
+            StartMethod::SpinTable { release_address } => {
+                core::ptr::write_volatile(release_address as *mut u64, entry);
+
+                // The core is polling with caches disabled, so the write must reach memory
+                asm!("dc civac, {}", in(reg) release_address, options(nostack));
+                dsb(SY);
+
+                sev();
+
+                Ok(())
+            }
+            StartMethod::Psci(conduit) => {
+                let mut result = PSCI_CPU_ON;
+
+                // Target affinity is the core ID, with no context required
+                match conduit {
+                    PsciConduit::Hvc => asm!(
+                        "hvc #0",
+                        inout("x0") result,
+                        in("x1") core as u64,
+                        in("x2") entry,
+                        in("x3") 0,
+                        clobber_abi("C"),
+                        options(nostack),
+                    ),
+                    PsciConduit::Smc => asm!(
+                        "smc #0",
+                        inout("x0") result,
+                        in("x1") core as u64,
+                        in("x2") entry,
+                        in("x3") 0,
+                        clobber_abi("C"),
+                        options(nostack),
+                    ),
+                }
+
+                match result as i64 {
+                    0 => Ok(()),
+                    error => Err(error),
+                }
+            }
+        }
+    }
+}
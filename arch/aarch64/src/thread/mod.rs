@@ -0,0 +1,97 @@
+use core::{arch::naked_asm, ops::Range};
+
+use crate::core_block::CoreBlock;
+
+/// Saved state of a thread which is not currently running. Only the callee-saved registers are
+/// kept, as the thread is always suspended within a call to [`switch`].
+#[repr(C)]
+pub struct ThreadContext {
+    /// Callee-saved registers `x19` to `x28`.
+    gpr: [u64; 10],
+    /// Frame pointer (`x29`).
+    fp: u64,
+    /// Link register (`x30`), which is where the thread resumes.
+    lr: u64,
+    /// Stack pointer.
+    sp: u64,
+
+    /// Number of nested IRQ handlers that the thread was running when it was suspended.
+    irq_depth: usize,
+    /// Lowest address of the thread's stack.
+    stack_start: usize,
+    /// Address just past the top of the thread's stack.
+    stack_end: usize,
+}
+
+impl ThreadContext {
+    /// Context of a thread which has not yet been suspended.
+    pub(crate) const EMPTY: Self = Self {
+        gpr: [0; 10],
+        fp: 0,
+        lr: 0,
+        sp: 0,
+        irq_depth: 0,
+        stack_start: 0,
+        stack_end: 0,
+    };
+
+    /// Context for a new thread, which will call `entry(arg)` on `stack`.
+    pub(crate) fn new(stack: Range<usize>, entry: extern "C" fn(usize) -> !, arg: usize) -> Self {
+        let mut gpr = [0; 10];
+        gpr[0] = entry as usize as u64;
+        gpr[1] = arg as u64;
+
+        Self {
+            gpr,
+            fp: 0,
+            lr: thread_start as usize as u64,
+            // The stack pointer must always be 16 byte aligned
+            sp: (stack.end & !0xf) as u64,
+            irq_depth: 0,
+            stack_start: stack.start,
+            stack_end: stack.end,
+        }
+    }
+}
+
+/// Suspend the current thread into `from`, and resume the thread in `to`.
+///
+/// # Safety
+///
+/// See [`lib_kernel::Arch::switch_thread`].
+pub(crate) unsafe fn switch(from: *mut ThreadContext, to: *const ThreadContext) {
+    // A thread may be suspended from within an IRQ handler, so the nesting belongs to the thread
+    // rather than the core.
+    let core = CoreBlock::current();
+    (*from).irq_depth = core.irq_depth();
+    core.set_irq_depth((*to).irq_depth);
+
+    let stack = core.stack();
+    (*from).stack_start = stack.start;
+    (*from).stack_end = stack.end;
+    core.set_stack((*to).stack_start..(*to).stack_end);
+
+    switch_context(from, to);
+}
+
+/// Save the callee-saved registers into `from`, and restore them from `to`.
+///
+/// # Safety
+///
+/// Both pointers must be valid, and `to` must have been saved by this function or created by
+/// [`ThreadContext::new`].
+#[naked]
+unsafe extern "C" fn switch_context(_from: *mut ThreadContext, _to: *const ThreadContext) {
+    naked_asm!(include_str!("switch.s"))
+}
+
+/// First code run by a new thread, which calls the entry point held in its context.
+///
+/// # Safety
+///
+/// Must only be returned to by [`switch_context`], with a context created by
+/// [`ThreadContext::new`].
+#[naked]
+unsafe extern "C" fn thread_start() -> ! {
+    naked_asm!(include_str!("start.s"))
+}
@@ -0,0 +1,8 @@
+    // Pass the argument to the entry point, as set up by `ThreadContext::new`
+    mov     x0,  x20
+
+    // Terminate the frame chain, as the thread has no caller to return to
+    mov     x29, xzr
+    mov     x30, xzr
+
+    br      x19
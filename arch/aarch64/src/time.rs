@@ -1,28 +1,108 @@
+use core::marker::PhantomData;
+
 use aarch64_cpu::{asm, registers::*};
-use core::time::Duration;
-use uom::si::{
-    f64::{Frequency, Ratio},
-    frequency::hertz,
-    ratio::ratio,
-};
+use lib_kernel::time::{ClockEvent, ClockSource, DeadlinePassed};
 
 use crate::{Aarch64, Aarch64Config};
 
 impl<C: Aarch64Config> Aarch64<C> {
-    /// Returns the frequency in Hz.
-    pub fn frequency() -> Frequency {
+    /// Raw frequency of the system counter in Hz.
+    pub(crate) fn counter_frequency() -> u64 {
         // NOTE: Although a 64 bit register, only bits [31:0] contain the frequency.
-        Frequency::new::<hertz>((CNTFRQ_EL0.get() & (u32::MAX as u64)) as f64)
+        CNTFRQ_EL0.get() & (u32::MAX as u64)
     }
 
-    pub fn uptime() -> Duration {
+    /// Current value of the physical system counter.
+    pub(crate) fn counter() -> u64 {
+        // Prevent the counter from being read ahead of time.
         asm::barrier::isb(asm::barrier::SY);
 
-        let count = CNTPCT_EL0.get();
+        CNTPCT_EL0.get()
+    }
+
+    /// Arm the EL1 physical timer to fire once the counter reaches `deadline`.
+    pub(crate) fn set_timer(deadline: u64) {
+        // D19-7878: The timer condition is met when `CNTPCT_EL0 - CNTP_CVAL_EL0 >= 0`.
+        CNTP_CVAL_EL0.set(deadline);
+
+        CNTP_CTL_EL0.write(CNTP_CTL_EL0::ENABLE::SET + CNTP_CTL_EL0::IMASK::CLEAR);
+    }
+
+    /// Disarm the EL1 physical timer, clearing any pending interrupt.
+    pub(crate) fn cancel_timer() {
+        CNTP_CTL_EL0.write(CNTP_CTL_EL0::ENABLE::CLEAR + CNTP_CTL_EL0::IMASK::SET);
+    }
+}
+
+/// The generic timer of each core, described as a clock source and clock event device.
+pub struct GenericTimer<Config> {
+    // The configuration is only used at compile time, so never prevents sharing between cores
+    _config: PhantomData<fn() -> Config>,
+}
 
-        let count = Ratio::new::<ratio>(count as f64);
-        let frequency = Self::frequency();
+impl<C: Aarch64Config> GenericTimer<C> {
+    /// Create a new instance, which refers to the timer of whichever core it is used on.
+    pub const fn new() -> Self {
+        Self {
+            _config: PhantomData,
+        }
+    }
+}
+
+impl<C: Aarch64Config> Default for GenericTimer<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The system counter is read with a single instruction and is synchronised between all cores.
+impl<C: Aarch64Config> ClockSource for GenericTimer<C> {
+    fn name(&self) -> &'static str {
+        "ARM generic timer"
+    }
+
+    fn rating(&self) -> u32 {
+        400
+    }
+
+    fn frequency(&self) -> u64 {
+        Aarch64::<C>::counter_frequency()
+    }
+
+    fn read(&self) -> u64 {
+        Aarch64::<C>::counter()
+    }
+}
+
+/// Each core has its own EL1 physical timer, which fires immediately if its deadline has passed.
+impl<C: Aarch64Config> ClockEvent for GenericTimer<C> {
+    fn name(&self) -> &'static str {
+        "ARM generic timer"
+    }
+
+    fn rating(&self) -> u32 {
+        400
+    }
+
+    fn per_core(&self) -> bool {
+        true
+    }
+
+    fn frequency(&self) -> u64 {
+        Aarch64::<C>::counter_frequency()
+    }
+
+    fn counter(&self) -> u64 {
+        Aarch64::<C>::counter()
+    }
+
+    fn set_deadline(&self, deadline: u64) -> Result<(), DeadlinePassed> {
+        Aarch64::<C>::set_timer(deadline);
+
+        Ok(())
+    }
 
-        Duration::try_from(count / frequency).unwrap()
+    fn cancel(&self) {
+        Aarch64::<C>::cancel_timer();
     }
 }
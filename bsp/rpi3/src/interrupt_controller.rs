@@ -0,0 +1,64 @@
+//! BCM2835 interrupt controller, which collects the interrupts of all peripherals on the board and
+//! presents them to the ARM cores as the single 'GPU' interrupt.
+//!
+//! _(reference: BCM2835 ARM Peripherals, section 7)_
+
+use tock_registers::{interfaces::*, register_structs, registers::*};
+
+/// Base address of the interrupt controller.
+const BASE_ADDRESS: usize = 0x3F00_B200;
+
+/// Number of interrupts covered by each pending/enable register.
+const IRQS_PER_REGISTER: usize = 32;
+
+/// Interrupt number of each compare channel of the system timer.
+pub const SYSTEM_TIMER_IRQS: [usize; 4] = [0, 1, 2, 3];
+
+/// Interrupt number of the auxiliary peripherals, including the mini UART.
+pub const AUX_IRQ: usize = 29;
+
+/// Interrupt number of each bank of GPIO pins, which cover pins 0 to 27, 28 to 45 and 46 to 53.
+pub const GPIO_IRQS: [usize; 3] = [49, 50, 51];
+
+/// Interrupt number of the PL011 UART.
+pub const UART_IRQ: usize = 57;
+
+/// Fetch the register block of the interrupt controller.
+fn registers() -> &'static RegisterBlock {
+    // Safety: `BASE_ADDRESS` is the fixed location of the interrupt controller on this board.
+    unsafe { &*(BASE_ADDRESS as *const RegisterBlock) }
+}
+
+/// Split an interrupt number into the register index and bit that represent it.
+fn position(irq: usize) -> (usize, u32) {
+    (irq / IRQS_PER_REGISTER, 1 << (irq % IRQS_PER_REGISTER))
+}
+
+/// Allow `irq` to raise the GPU interrupt.
+pub fn enable(irq: usize) {
+    let (index, bit) = position(irq);
+
+    // Writing a `0` bit has no effect, so other interrupts are untouched
+    registers().ENABLE_IRQS[index].set(bit);
+}
+
+/// Determine whether `irq` is currently pending.
+pub fn is_pending(irq: usize) -> bool {
+    let (index, bit) = position(irq);
+
+    registers().IRQ_PENDING[index].get() & bit != 0
+}
+
+register_structs! {
+    #[allow(non_snake_case)]
+    RegisterBlock {
+        (0x00 => IRQ_BASIC_PENDING: ReadOnly<u32>),
+        (0x04 => IRQ_PENDING: [ReadOnly<u32>; 2]),
+        (0x0C => FIQ_CONTROL: ReadWrite<u32>),
+        (0x10 => ENABLE_IRQS: [WriteOnly<u32>; 2]),
+        (0x18 => ENABLE_BASIC_IRQS: WriteOnly<u32>),
+        (0x1C => DISABLE_IRQS: [WriteOnly<u32>; 2]),
+        (0x24 => DISABLE_BASIC_IRQS: WriteOnly<u32>),
+        (0x28 => @END),
+    }
+}
@@ -1,21 +1,105 @@
 #![no_std]
 
-use core::{fmt::Write, marker::PhantomData};
+mod interrupt_controller;
+mod local_peripherals;
 
-use aarch64::{Aarch64, Aarch64Config};
-use lib_kernel::Bsp;
-use pl011::{Initialised, Pl011};
-use spin::mutex::SpinMutex;
+use core::{
+    fmt::{self, Write},
+    marker::PhantomData,
+    time::Duration,
+};
+
+use aarch64::{Aarch64, Aarch64Config, Delay, ExceptionFrame, GenericTimer, StartMethod};
+use bcm2835_aux_uart::MiniUart;
+use bcm2835_gpio::{Detect, Function, Gpio, Input, Pull, PIN_COUNT};
+use bcm2835_mailbox::{
+    tags::{Clock, GetClockRate},
+    Mailbox,
+};
+use bcm2835_system_timer::{CompareChannel, SystemTimer};
+use bcm2835_watchdog::Watchdog;
+use lib_kernel::{
+    console::{ReceiveErrors, Uart},
+    gpio::{self, GpioError, Trigger},
+    ipi::Ipi,
+    ring_buffer::{Consumer, Producer, RingBuffer},
+    sync::{IrqSpinMutex, Once},
+    time::{ClockEvent, ClockSource},
+    Arch, Bsp, Interrupt, PanicPolicy,
+};
+use pl011::Pl011;
+
+pub use bcm2835_mailbox as mailbox;
 
 const PL011_ADDRESS: usize = 0x3F201000;
-type Uart = Pl011<PL011_ADDRESS, Initialised>;
+
+/// Base address of the auxiliary peripherals block, which holds the mini UART.
+const AUX_ADDRESS: usize = 0x3F215000;
+
+/// Frequency of the core clock that the mini UART's baud rate is derived from, if it can't be
+/// queried from the firmware. The firmware fixes it at 250 MHz when `enable_uart=1` is set.
+const DEFAULT_CORE_CLOCK: u32 = 250_000_000;
+
+/// Baud rate of the mini UART, to match the PL011.
+const MINI_UART_BAUD_RATE: u32 = 921_600;
+
+/// GPIO pins on the header which are connected to the debug console.
+const UART_TX_PIN: usize = 14;
+const UART_RX_PIN: usize = 15;
+
+const GPIO_ADDRESS: usize = 0x3F200000;
+
+const MAILBOX_ADDRESS: usize = 0x3F00B880;
+
+const SYSTEM_TIMER_ADDRESS: usize = 0x3F003000;
+
+const POWER_MANAGEMENT_ADDRESS: usize = 0x3F100000;
+
+/// Compare channel of the system timer which is available to the kernel, as the firmware uses
+/// channels 0 and 2.
+const SYSTEM_TIMER_CHANNEL: usize = 3;
+
+/// Address that the firmware's spin table polls for the first core. Each following core polls the
+/// next 64 bit address.
+const SPIN_TABLE_BASE_ADDRESS: usize = 0xd8;
+
+/// Number of received bytes that can be buffered before they must be read.
+const UART_RX_BUFFER_SIZE: usize = 256;
+/// Number of bytes that can be queued for transmission before writers must wait.
+const UART_TX_BUFFER_SIZE: usize = 4096;
 
 /// Instance of this BSP. Config is used as a generic paramter so that it can be evaluated at
 /// compile time.
 pub struct Rpi3<Config> {
     _config: PhantomData<Config>,
 
-    uart: SpinMutex<Option<Uart>>,
+    /// Guards against the board being initialised more than once.
+    initialised: Once<Aarch64<ArchConfig<Config>>>,
+
+    /// Pins of the chip, which are connected to the header and on-board peripherals.
+    gpio: Gpio<GPIO_ADDRESS, Aarch64<ArchConfig<Config>>>,
+    /// Trigger of each pin which raises interrupts for the kernel.
+    gpio_triggers: IrqSpinMutex<Aarch64<ArchConfig<Config>>, [Option<Trigger>; PIN_COUNT]>,
+
+    /// UART used as the debug console. IRQs are masked whilst it is locked, as it is also used by
+    /// the IRQ handler.
+    uart: IrqSpinMutex<Aarch64<ArchConfig<Config>>, Option<ConsoleUart>>,
+    /// Bytes received by the UART which are yet to be read.
+    uart_rx: RingBuffer<UART_RX_BUFFER_SIZE>,
+    /// Bytes written to the UART which are yet to be transmitted.
+    uart_tx: RingBuffer<UART_TX_BUFFER_SIZE>,
+
+    /// Mailbox to the VideoCore firmware, which can describe and control the rest of the board.
+    mailbox: Mailbox<MAILBOX_ADDRESS, Aarch64<ArchConfig<Config>>>,
+
+    /// Timer of each core, which is the preferred clock.
+    generic_timer: GenericTimer<ArchConfig<Config>>,
+    /// Free-running timer on the peripheral bus, independent of the cores.
+    system_timer: SystemTimer<SYSTEM_TIMER_ADDRESS>,
+    system_timer_compare: CompareChannel<SYSTEM_TIMER_ADDRESS, SYSTEM_TIMER_CHANNEL>,
+
+    /// Watchdog of the power management block, which is also used to reset the board.
+    watchdog: Watchdog<POWER_MANAGEMENT_ADDRESS>,
 }
 
 impl<C: Rpi3Config> Rpi3<C> {
@@ -23,9 +107,91 @@ impl<C: Rpi3Config> Rpi3<C> {
     pub const fn new() -> Self {
         Self {
             _config: PhantomData,
-            uart: SpinMutex::new(None),
+            initialised: Once::new(),
+            gpio: Gpio::new(),
+            gpio_triggers: IrqSpinMutex::new([None; PIN_COUNT]),
+            uart: IrqSpinMutex::new(None),
+            uart_rx: RingBuffer::new(),
+            uart_tx: RingBuffer::new(),
+            mailbox: Mailbox::new(),
+            generic_timer: GenericTimer::new(),
+            system_timer: SystemTimer::new(),
+            system_timer_compare: CompareChannel::new(),
+            watchdog: Watchdog::new(),
         }
     }
+
+    /// Mailbox to the VideoCore firmware, for querying the board at runtime.
+    pub fn mailbox(&self) -> &Mailbox<MAILBOX_ADDRESS, Aarch64<ArchConfig<C>>> {
+        &self.mailbox
+    }
+
+    /// Connect the debug console to its pins, rather than relying on the firmware to have done
+    /// so. The receive pin is pulled up, so that a disconnected line is idle rather than floating.
+    fn connect_uart_pins(&self) {
+        let mut delay = Delay::<ArchConfig<C>>::new();
+        let function = C::DEBUG_CONSOLE.pin_function();
+
+        let mut tx = self
+            .gpio
+            .pin(UART_TX_PIN)
+            .expect("UART transmit pin to be unclaimed")
+            .into_alternate(function);
+        tx.set_pull(Pull::None, &mut delay);
+
+        let mut rx = self
+            .gpio
+            .pin(UART_RX_PIN)
+            .expect("UART receive pin to be unclaimed")
+            .into_alternate(function);
+        rx.set_pull(Pull::Up, &mut delay);
+    }
+
+    /// Current rate of the core clock, in Hz.
+    fn core_clock(&self) -> u32 {
+        self.mailbox
+            .request::<GetClockRate>(Clock::Core)
+            .ok()
+            .map(|clock| clock.rate)
+            .filter(|rate| *rate != 0)
+            .unwrap_or(DEFAULT_CORE_CLOCK)
+    }
+
+    /// Acknowledge the event of every pin which raises interrupts for the kernel, passing each to
+    /// `handler`.
+    fn handle_gpio_irq<F>(&self, handler: &mut F)
+    where
+        F: FnMut(Interrupt<'_>),
+    {
+        let (edges, levels) = {
+            let triggers = self.gpio_triggers.lock();
+            let events = self.gpio.events();
+
+            let pins_with = |level| {
+                (0..PIN_COUNT)
+                    .filter(|pin| triggers[*pin].is_some_and(|trigger| trigger.is_level() == level))
+                    .fold(0u64, |pins, pin| pins | 1 << pin)
+            };
+
+            (events & pins_with(false), events & pins_with(true))
+        };
+
+        // Edges are acknowledged first, so that another edge during the handler isn't lost. Levels
+        // are acknowledged afterwards, once the handler has had a chance to release the pin.
+        self.gpio.clear_events(edges);
+
+        (0..PIN_COUNT)
+            .filter(|pin| (edges | levels) & 1 << pin != 0)
+            .for_each(|pin| handler(Interrupt::Gpio(pin)));
+
+        self.gpio.clear_events(levels);
+    }
+
+    /// Count of all errors encountered whilst receiving on the debug console, if it is
+    /// initialised.
+    pub fn debug_console_errors(&self) -> Option<ReceiveErrors> {
+        Some(self.uart.lock().as_ref()?.receive_errors())
+    }
 }
 
 /// Provide required information to the kernel by implementing the [`Bsp`] trait.
@@ -33,20 +199,411 @@ impl<C: Rpi3Config> Bsp for Rpi3<C> {
     type Arch = Aarch64<ArchConfig<C>>;
 
     fn initialise(&self) {
-        // TODO: Verify that board hasn't already been initialised
-        let mut uart = self.uart.lock();
+        // Re-initialising the UART would discard anything it is part way through transmitting
+        self.initialised.call_once(|| {
+            Self::Arch::calibrate_delay();
+
+            self.connect_uart_pins();
+            *self.uart.lock() = Some(match C::DEBUG_CONSOLE {
+                DebugConsole::Pl011 => {
+                    ConsoleUart::Pl011(Pl011::<PL011_ADDRESS>::new().initialise())
+                }
+                DebugConsole::MiniUart => ConsoleUart::MiniUart(
+                    MiniUart::<AUX_ADDRESS>::new()
+                        .initialise(self.core_clock(), MINI_UART_BAUD_RATE),
+                ),
+            });
+            interrupt_controller::enable(C::DEBUG_CONSOLE.irq());
+            interrupt_controller::GPIO_IRQS
+                .into_iter()
+                .for_each(interrupt_controller::enable);
+            interrupt_controller::enable(
+                interrupt_controller::SYSTEM_TIMER_IRQS[SYSTEM_TIMER_CHANNEL],
+            );
+        });
+    }
+
+    fn initialise_core(&self) {
+        let core = Self::Arch::core_id();
 
-        *uart = Some(Uart::new().initialise());
+        // Allow the architecture timer and other cores to interrupt this core
+        local_peripherals::enable_timer_irq(core);
+        local_peripherals::enable_ipi_irq(core);
+        local_peripherals::enable_nmi_fiq(core);
+    }
+
+    fn start_secondary_cores(&self) {
+        let boot_core = <ArchConfig<C> as Aarch64Config>::BOOT_CORE_ID;
+
+        for core in (0..local_peripherals::CORE_COUNT).filter(|core| *core != boot_core) {
+            let method = StartMethod::SpinTable {
+                release_address: SPIN_TABLE_BASE_ADDRESS + core * 8,
+            };
+
+            // Safety: The firmware holds all secondary cores in the spin table, and this is the
+            // only place that releases them.
+            unsafe { Self::Arch::start_core(core, method) }
+                .expect("spin table to always release cores");
+        }
     }
 
     fn with_debug_console<F, T>(&self, f: F) -> Option<T>
     where
         F: FnOnce(&mut dyn Write) -> T,
     {
-        // Use the PL011 peripheral as a debug console.
+        // Use whichever UART the configuration chose as the debug console, buffering its output.
         let mut guard = self.uart.lock();
+        let uart = guard.as_mut()?;
 
-        Some(f(guard.as_mut()?))
+        // Both ends of the buffer are only ever claimed whilst the UART is locked
+        let mut writer = BufferedUart {
+            uart,
+            producer: self.uart_tx.producer().expect("UART lock to be held"),
+            consumer: self.uart_tx.consumer().expect("UART lock to be held"),
+        };
+
+        let result = f(&mut writer);
+
+        // Begin transmitting anything that was written
+        writer.uart.transmit(&mut writer.consumer);
+
+        Some(result)
+    }
+
+    fn with_debug_console_sync<F, T>(&self, f: F) -> Option<T>
+    where
+        F: FnOnce(&mut dyn Write) -> T,
+    {
+        // The lock may be held by this core, such as when panicking part way through writing, so
+        // waiting for it could deadlock
+        let Some(mut guard) = self.uart.try_lock() else {
+            if !self.initialised.is_completed() {
+                return None;
+            }
+
+            // Safety: The UART was initialised along with the board. Whoever holds the lock is
+            // either interrupted or being stopped, so at worst their output is interleaved.
+            let mut uart = unsafe { ConsoleUart::steal(C::DEBUG_CONSOLE) };
+
+            let result = f(&mut uart);
+
+            uart.flush();
+
+            return Some(result);
+        };
+        let uart = guard.as_mut()?;
+
+        // Send everything that is already queued, so output remains in order
+        uart.transmit_blocking(&mut self.uart_tx.consumer().expect("UART lock to be held"));
+
+        let result = f(uart);
+
+        uart.flush();
+
+        Some(result)
+    }
+
+    fn send_ipi(&self, core: usize, ipi: Ipi) {
+        local_peripherals::send_ipi(core, ipi.bit());
+    }
+
+    fn send_nmi(&self, core: usize) {
+        local_peripherals::send_nmi(core);
+    }
+
+    fn handle_nmi<F>(&self, handler: F)
+    where
+        F: FnOnce(),
+    {
+        if local_peripherals::take_nmi(Self::Arch::core_id()) {
+            handler();
+        }
+    }
+
+    fn read_debug_console(&self, buffer: &mut [u8]) -> usize {
+        // Another reader is active, so there is nothing available for this one
+        let Some(mut rx) = self.uart_rx.consumer() else {
+            return 0;
+        };
+
+        rx.pop_slice(buffer)
+    }
+
+    fn clock_sources(&self) -> impl Iterator<Item = &dyn ClockSource> {
+        [&self.generic_timer as &dyn ClockSource, &self.system_timer].into_iter()
+    }
+
+    fn clock_events(&self) -> impl Iterator<Item = &dyn ClockEvent> {
+        [
+            &self.generic_timer as &dyn ClockEvent,
+            &self.system_timer_compare,
+        ]
+        .into_iter()
+    }
+
+    fn reboot(&self) -> ! {
+        self.watchdog.reboot()
+    }
+
+    fn shutdown(&self) -> ! {
+        // The board can't be powered off, so the firmware is told to halt once it is reset
+        self.watchdog.halt()
+    }
+
+    fn start_watchdog(&self, timeout: Duration) -> Option<Duration> {
+        self.watchdog.start(timeout);
+
+        Some(timeout.min(bcm2835_watchdog::MAX_TIMEOUT))
+    }
+
+    fn kick_watchdog(&self) {
+        self.watchdog.kick();
+    }
+
+    fn stop_watchdog(&self) {
+        self.watchdog.stop();
+    }
+
+    fn enable_gpio_interrupt(
+        &self,
+        pin: usize,
+        trigger: Trigger,
+        pull: gpio::Pull,
+    ) -> Result<(), GpioError> {
+        if pin >= PIN_COUNT {
+            return Err(GpioError::InvalidPin);
+        }
+
+        let mut input = self.gpio.pin(pin).ok_or(GpioError::InUse)?.into_input();
+
+        let pull = match pull {
+            gpio::Pull::None => Pull::None,
+            gpio::Pull::Down => Pull::Down,
+            gpio::Pull::Up => Pull::Up,
+        };
+        input.set_pull(pull, &mut Delay::<ArchConfig<C>>::new());
+
+        let mut triggers = self.gpio_triggers.lock();
+
+        // Discard anything detected before the pin was configured
+        input.clear_event();
+
+        for detect in detects(trigger) {
+            input.enable_detect(*detect);
+        }
+
+        // The pin stays claimed once dropped, until the interrupt is disabled
+        triggers[pin] = Some(trigger);
+
+        Ok(())
+    }
+
+    fn disable_gpio_interrupt(&self, pin: usize) {
+        let mut triggers = self.gpio_triggers.lock();
+
+        let Some(trigger) = triggers.get_mut(pin).and_then(Option::take) else {
+            return;
+        };
+
+        // Safety: The pin was claimed and configured as an input when its trigger was set, and
+        // the input was dropped.
+        let mut input = unsafe { self.gpio.steal::<Input>(pin) };
+
+        for detect in detects(trigger) {
+            input.disable_detect(*detect);
+        }
+
+        input.clear_event();
+        input.release();
+    }
+
+    fn gpio_is_high(&self, pin: usize) -> Option<bool> {
+        (pin < PIN_COUNT).then(|| self.gpio.is_high(pin))
+    }
+
+    fn handle_irq<F>(&self, mut handler: F)
+    where
+        F: FnMut(Interrupt<'_>),
+    {
+        let core = Self::Arch::core_id();
+
+        if local_peripherals::timer_irq_pending(core) {
+            handler(Interrupt::ClockEvent(&self.generic_timer));
+        }
+
+        if local_peripherals::ipi_irq_pending(core) {
+            Ipi::from_bits(local_peripherals::take_ipi(core))
+                .for_each(|ipi| handler(Interrupt::Ipi(ipi)));
+        }
+
+        if !local_peripherals::gpu_irq_pending(core) {
+            return;
+        }
+
+        if interrupt_controller::is_pending(
+            interrupt_controller::SYSTEM_TIMER_IRQS[SYSTEM_TIMER_CHANNEL],
+        ) && self.system_timer_compare.take_match()
+        {
+            handler(Interrupt::ClockEvent(&self.system_timer_compare));
+        }
+
+        if interrupt_controller::GPIO_IRQS
+            .into_iter()
+            .any(interrupt_controller::is_pending)
+        {
+            self.handle_gpio_irq(&mut handler);
+        }
+
+        if interrupt_controller::is_pending(C::DEBUG_CONSOLE.irq()) {
+            // The IRQ handler is the only producer, so the buffer must be available
+            let mut rx = self
+                .uart_rx
+                .producer()
+                .expect("UART receive buffer to have a single producer");
+
+            if let Some(uart) = self.uart.lock().as_mut() {
+                let mut tx = self.uart_tx.consumer().expect("UART lock to be held");
+
+                uart.handle_interrupt(&mut rx, &mut tx);
+            }
+        }
+    }
+}
+
+/// Event detection which raises `trigger` on a pin.
+fn detects(trigger: Trigger) -> &'static [Detect] {
+    match trigger {
+        Trigger::RisingEdge => &[Detect::RisingEdge],
+        Trigger::FallingEdge => &[Detect::FallingEdge],
+        Trigger::BothEdges => &[Detect::RisingEdge, Detect::FallingEdge],
+        Trigger::High => &[Detect::High],
+        Trigger::Low => &[Detect::Low],
+    }
+}
+
+/// UART which is used as the debug console.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DebugConsole {
+    /// The PL011, which many configurations instead connect to the Bluetooth module.
+    Pl011,
+    /// The mini UART of the auxiliary peripherals. Its baud rate follows the core clock, which the
+    /// firmware must keep fixed.
+    MiniUart,
+}
+
+impl DebugConsole {
+    /// Alternate function which connects the UART to the header pins.
+    const fn pin_function(self) -> Function {
+        match self {
+            DebugConsole::Pl011 => Function::Alt0,
+            DebugConsole::MiniUart => Function::Alt5,
+        }
+    }
+
+    /// Interrupt number of the UART in the interrupt controller.
+    const fn irq(self) -> usize {
+        match self {
+            DebugConsole::Pl011 => interrupt_controller::UART_IRQ,
+            DebugConsole::MiniUart => interrupt_controller::AUX_IRQ,
+        }
+    }
+}
+
+/// The initialised UART chosen by [`Rpi3Config::DEBUG_CONSOLE`].
+enum ConsoleUart {
+    Pl011(Pl011<PL011_ADDRESS, pl011::Initialised>),
+    MiniUart(MiniUart<AUX_ADDRESS, bcm2835_aux_uart::Initialised>),
+}
+
+impl ConsoleUart {
+    /// Create another handle to the initialised `console`, for use when the UART lock can't be
+    /// taken.
+    ///
+    /// # Safety
+    ///
+    /// See [`Pl011::steal`] and [`MiniUart::steal`].
+    unsafe fn steal(console: DebugConsole) -> Self {
+        match console {
+            DebugConsole::Pl011 => ConsoleUart::Pl011(Pl011::steal()),
+            DebugConsole::MiniUart => ConsoleUart::MiniUart(MiniUart::steal()),
+        }
+    }
+}
+
+impl Write for ConsoleUart {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        match self {
+            ConsoleUart::Pl011(uart) => uart.write_str(s),
+            ConsoleUart::MiniUart(uart) => uart.write_str(s),
+        }
+    }
+}
+
+impl Uart for ConsoleUart {
+    fn handle_interrupt<const RX: usize, const TX: usize>(
+        &mut self,
+        rx: &mut Producer<'_, RX>,
+        tx: &mut Consumer<'_, TX>,
+    ) {
+        match self {
+            ConsoleUart::Pl011(uart) => uart.handle_interrupt(rx, tx),
+            ConsoleUart::MiniUart(uart) => uart.handle_interrupt(rx, tx),
+        }
+    }
+
+    fn transmit<const N: usize>(&mut self, tx: &mut Consumer<'_, N>) {
+        match self {
+            ConsoleUart::Pl011(uart) => uart.transmit(tx),
+            ConsoleUart::MiniUart(uart) => uart.transmit(tx),
+        }
+    }
+
+    fn transmit_blocking<const N: usize>(&mut self, tx: &mut Consumer<'_, N>) {
+        match self {
+            ConsoleUart::Pl011(uart) => uart.transmit_blocking(tx),
+            ConsoleUart::MiniUart(uart) => uart.transmit_blocking(tx),
+        }
+    }
+
+    fn flush(&self) {
+        match self {
+            ConsoleUart::Pl011(uart) => uart.flush(),
+            ConsoleUart::MiniUart(uart) => uart.flush(),
+        }
+    }
+
+    fn receive_errors(&self) -> ReceiveErrors {
+        match self {
+            ConsoleUart::Pl011(uart) => uart.receive_errors(),
+            ConsoleUart::MiniUart(uart) => uart.receive_errors(),
+        }
+    }
+}
+
+/// Writer which queues output to be transmitted by the UART in the background.
+struct BufferedUart<'a> {
+    uart: &'a mut ConsoleUart,
+    producer: Producer<'a, UART_TX_BUFFER_SIZE>,
+    consumer: Consumer<'a, UART_TX_BUFFER_SIZE>,
+}
+
+impl Write for BufferedUart<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut bytes = s.as_bytes();
+
+        while !bytes.is_empty() {
+            let written = self.producer.push_slice(bytes);
+            bytes = &bytes[written..];
+
+            if !bytes.is_empty() {
+                // The buffer is full, so make room by sending directly. Interrupts may be masked
+                // (or this may be the interrupt handler), so it's not possible to wait for the
+                // buffer to drain.
+                self.uart.transmit_blocking(&mut self.consumer);
+            }
+        }
+
+        Ok(())
     }
 }
 
@@ -60,6 +617,26 @@ impl<C: Rpi3Config> Default for Rpi3<C> {
 pub trait Rpi3Config {
     /// Entry point to the kernel, which will be called once the device has booted.
     const KERNEL_MAIN: fn() -> !;
+
+    /// Entry point to the kernel for each secondary core, once it has been started.
+    const SECONDARY_MAIN: fn() -> !;
+
+    /// Handler for IRQs, which is expected to call [`Bsp::handle_irq`].
+    const IRQ_HANDLER: fn();
+
+    /// Handler for non-maskable interrupts, which is expected to call [`Bsp::handle_nmi`]. It is
+    /// given the state of the interrupted code.
+    const NMI_HANDLER: fn(&ExceptionFrame);
+
+    /// UART to use as the debug console, which is connected to GPIO 14 and 15 on the header.
+    const DEBUG_CONSOLE: DebugConsole = DebugConsole::Pl011;
+
+    /// Action the kernel takes after any panic.
+    const PANIC_POLICY: PanicPolicy = PanicPolicy::Halt;
+
+    /// GPIO pin of a button which shuts the board down, by connecting the pin to ground whilst
+    /// pressed. GPIO 3 is conventional, as the firmware also starts the board when it is pulled low.
+    const POWER_BUTTON: Option<usize> = None;
 }
 
 /// Configuration for the Aarch64 core suitable to run on this board.
@@ -69,4 +646,9 @@ pub struct ArchConfig<Rpi3Config> {
 impl<C: Rpi3Config> Aarch64Config for ArchConfig<C> {
     const BOOT_CORE_ID: usize = 0;
     const KERNEL_MAIN: fn() -> ! = C::KERNEL_MAIN;
+    const SECONDARY_MAIN: fn() -> ! = C::SECONDARY_MAIN;
+    const IRQ_HANDLER: fn() = C::IRQ_HANDLER;
+    const NMI_HANDLER: fn(&ExceptionFrame) = C::NMI_HANDLER;
+    // The system counter is driven by the 19.2 MHz crystal
+    const COUNTER_FREQUENCY: u32 = 19_200_000;
 }
@@ -0,0 +1,168 @@
+//! ARM local peripherals of the BCM2836 (and BCM2837), which route interrupts to each core.
+//!
+//! _(reference: BCM2836 ARM-local peripherals, QA7 rev 3.4)_
+
+use tock_registers::{interfaces::*, register_bitfields, register_structs, registers::*};
+
+/// Base address of the local peripherals.
+const BASE_ADDRESS: usize = 0x4000_0000;
+
+/// Number of cores that the local peripherals serve.
+pub const CORE_COUNT: usize = 4;
+
+/// Number of mailboxes that each core has.
+const MAILBOXES_PER_CORE: usize = 4;
+
+/// Mailbox of each core which is used for inter-processor interrupts.
+const IPI_MAILBOX: usize = 0;
+
+/// Mailbox of each core which is used for non-maskable interrupts, routed to its FIQ line.
+const NMI_MAILBOX: usize = 1;
+
+/// Fetch the register block of the local peripherals.
+fn registers() -> &'static RegisterBlock {
+    // Safety: `BASE_ADDRESS` is the fixed location of the local peripherals on this board.
+    unsafe { &*(BASE_ADDRESS as *const RegisterBlock) }
+}
+
+/// Route the non-secure physical timer interrupt of `core` to its IRQ line.
+pub fn enable_timer_irq(core: usize) {
+    registers().CORE_TIMER_IRQCNTL[core].write(TIMER_IRQCNTL::CNTPNSIRQ_IRQ::Enabled);
+}
+
+/// Determine whether the non-secure physical timer interrupt is pending for `core`.
+pub fn timer_irq_pending(core: usize) -> bool {
+    registers().CORE_IRQ_SOURCE[core].is_set(IRQ_SOURCE::CNTPNSIRQ)
+}
+
+/// Determine whether the GPU interrupt (from the interrupt controller) is pending for `core`.
+pub fn gpu_irq_pending(core: usize) -> bool {
+    registers().CORE_IRQ_SOURCE[core].is_set(IRQ_SOURCE::GPU)
+}
+
+/// Route the IPI mailbox interrupt of `core` to its IRQ line.
+pub fn enable_ipi_irq(core: usize) {
+    registers().CORE_MAILBOX_IRQCNTL[core].modify(MAILBOX_IRQCNTL::MAILBOX0_IRQ::Enabled);
+}
+
+/// Route the NMI mailbox interrupt of `core` to its FIQ line.
+pub fn enable_nmi_fiq(core: usize) {
+    registers().CORE_MAILBOX_IRQCNTL[core].modify(MAILBOX_IRQCNTL::MAILBOX1_FIQ::Enabled);
+}
+
+/// Raise the FIQ of `core` using its NMI mailbox.
+pub fn send_nmi(core: usize) {
+    registers().CORE_MAILBOX_SET[core * MAILBOXES_PER_CORE + NMI_MAILBOX].set(1);
+}
+
+/// Clear the NMI mailbox of `core`, returning whether it was set.
+pub fn take_nmi(core: usize) -> bool {
+    if !registers().CORE_FIQ_SOURCE[core].is_set(IRQ_SOURCE::MAILBOX1) {
+        return false;
+    }
+
+    let mailbox = &registers().CORE_MAILBOX_CLEAR[core * MAILBOXES_PER_CORE + NMI_MAILBOX];
+    mailbox.set(mailbox.get());
+
+    true
+}
+
+/// Determine whether the IPI mailbox interrupt is pending for `core`.
+pub fn ipi_irq_pending(core: usize) -> bool {
+    registers().CORE_IRQ_SOURCE[core].is_set(IRQ_SOURCE::MAILBOX0)
+}
+
+/// Set `bits` in the IPI mailbox of `core`, raising its interrupt.
+pub fn send_ipi(core: usize, bits: u32) {
+    registers().CORE_MAILBOX_SET[core * MAILBOXES_PER_CORE + IPI_MAILBOX].set(bits);
+}
+
+/// Take all bits that are set in the IPI mailbox of `core`, clearing its interrupt.
+pub fn take_ipi(core: usize) -> u32 {
+    let mailbox = &registers().CORE_MAILBOX_CLEAR[core * MAILBOXES_PER_CORE + IPI_MAILBOX];
+
+    let bits = mailbox.get();
+    mailbox.set(bits);
+
+    bits
+}
+
+register_bitfields! {
+    u32,
+
+    /// Core timers interrupt control
+    TIMER_IRQCNTL [
+        /// nCNTVIRQ IRQ control
+        CNTVIRQ_IRQ OFFSET(3) NUMBITS(1) [
+            Disabled = 0,
+            Enabled = 1,
+        ],
+
+        /// nCNTHPIRQ IRQ control
+        CNTHPIRQ_IRQ OFFSET(2) NUMBITS(1) [
+            Disabled = 0,
+            Enabled = 1,
+        ],
+
+        /// nCNTPNSIRQ IRQ control
+        CNTPNSIRQ_IRQ OFFSET(1) NUMBITS(1) [
+            Disabled = 0,
+            Enabled = 1,
+        ],
+
+        /// nCNTPSIRQ IRQ control
+        CNTPSIRQ_IRQ OFFSET(0) NUMBITS(1) [
+            Disabled = 0,
+            Enabled = 1,
+        ],
+    ],
+
+    /// Core mailboxes interrupt control
+    MAILBOX_IRQCNTL [
+        /// Mailbox-1 FIQ control (overrides the IRQ control)
+        MAILBOX1_FIQ OFFSET(5) NUMBITS(1) [
+            Disabled = 0,
+            Enabled = 1,
+        ],
+
+        /// Mailbox-0 IRQ control
+        MAILBOX0_IRQ OFFSET(0) NUMBITS(1) [
+            Disabled = 0,
+            Enabled = 1,
+        ],
+    ],
+
+    /// Core interrupt source
+    IRQ_SOURCE [
+        /// Local timer interrupt
+        LOCAL_TIMER OFFSET(11) NUMBITS(1) [],
+        /// GPU interrupt (can be high in one core only)
+        GPU OFFSET(8) NUMBITS(1) [],
+        /// Mailbox 1 interrupt
+        MAILBOX1 OFFSET(5) NUMBITS(1) [],
+        /// Mailbox 0 interrupt
+        MAILBOX0 OFFSET(4) NUMBITS(1) [],
+        /// CNTVIRQ interrupt
+        CNTVIRQ OFFSET(3) NUMBITS(1) [],
+        /// CNTHPIRQ interrupt
+        CNTHPIRQ OFFSET(2) NUMBITS(1) [],
+        /// CNTPNSIRQ interrupt
+        CNTPNSIRQ OFFSET(1) NUMBITS(1) [],
+        /// CNTPSIRQ interrupt
+        CNTPSIRQ OFFSET(0) NUMBITS(1) [],
+    ],
+}
+
+register_structs! {
+    #[allow(non_snake_case)]
+    RegisterBlock {
+        (0x00 => _reserved1),
+        (0x40 => CORE_TIMER_IRQCNTL: [ReadWrite<u32, TIMER_IRQCNTL::Register>; CORE_COUNT]),
+        (0x50 => CORE_MAILBOX_IRQCNTL: [ReadWrite<u32, MAILBOX_IRQCNTL::Register>; CORE_COUNT]),
+        (0x60 => CORE_IRQ_SOURCE: [ReadOnly<u32, IRQ_SOURCE::Register>; CORE_COUNT]),
+        (0x70 => CORE_FIQ_SOURCE: [ReadOnly<u32, IRQ_SOURCE::Register>; CORE_COUNT]),
+        (0x80 => CORE_MAILBOX_SET: [WriteOnly<u32>; CORE_COUNT * MAILBOXES_PER_CORE]),
+        (0xC0 => CORE_MAILBOX_CLEAR: [ReadWrite<u32>; CORE_COUNT * MAILBOXES_PER_CORE]),
+        (0x100 => @END),
+    }
+}
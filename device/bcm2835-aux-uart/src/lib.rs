@@ -0,0 +1,297 @@
+//! Driver for the mini UART of the BCM2835 auxiliary peripherals block.
+//!
+//! The mini UART is a cut down 16550, with 8 byte FIFOs and no detection of framing or parity
+//! errors. Its baud rate is derived from the core (VPU) clock, so the firmware must keep the core
+//! clock fixed whilst it is in use (as it does with `enable_uart=1`).
+//!
+//! Several fields are described incorrectly in the BCM2835 ARM Peripherals document. These match
+//! the corrections in its errata, and the driver in Linux
+//! (`drivers/tty/serial/8250/8250_bcm2835aux.c`).
+//!
+//! _(reference: BCM2835 ARM Peripherals, section 2)_
+
+#![no_std]
+
+use core::{fmt, marker::PhantomData};
+
+use lib_kernel::{
+    console::{ReceiveErrors, Uart},
+    ring_buffer::{Consumer, Producer},
+};
+use tock_registers::{interfaces::*, register_bitfields, register_structs, registers::*};
+
+pub enum Uninitialised {}
+pub enum Initialised {}
+
+/// The mini UART, where `BASE_ADDRESS` is the start of the auxiliary peripherals block.
+pub struct MiniUart<const BASE_ADDRESS: usize, I = Uninitialised> {
+    _init_state: PhantomData<I>,
+
+    /// Running count of errors encountered whilst receiving.
+    receive_errors: ReceiveErrors,
+}
+
+impl<const BASE_ADDRESS: usize, I> MiniUart<BASE_ADDRESS, I> {
+    /// Create a new, uninitialised mini UART instance.
+    pub fn new() -> MiniUart<BASE_ADDRESS, Uninitialised> {
+        MiniUart {
+            _init_state: PhantomData,
+            receive_errors: ReceiveErrors::default(),
+        }
+    }
+
+    /// Fetch the register block of this instance.
+    ///
+    /// # Safety
+    ///
+    /// `BASE_ADDRESS` must be a valid memory address, and point to the start of the memory-mapped
+    /// registers of the auxiliary peripherals block.
+    unsafe fn registers(&self) -> &'static RegisterBlock {
+        &*(BASE_ADDRESS as *const RegisterBlock)
+    }
+}
+
+impl<const BASE_ADDRESS: usize> MiniUart<BASE_ADDRESS, Uninitialised> {
+    /// Initialise the peripheral for `baud_rate` and 8N1, where the core clock runs at
+    /// `core_clock` Hz.
+    ///
+    /// The baud rate is the core clock divided by a multiple of 8, so the closest rate possible is
+    /// used.
+    pub fn initialise(
+        self,
+        core_clock: u32,
+        baud_rate: u32,
+    ) -> MiniUart<BASE_ADDRESS, Initialised> {
+        let registers = unsafe { self.registers() };
+
+        // The other auxiliary peripherals share the enable register, so must be left untouched
+        registers.AUX_ENABLES.modify(AUX_ENABLES::MINI_UART::SET);
+
+        // Disable the transmitter and receiver whilst configuring
+        registers.AUX_MU_CNTL_REG.set(0);
+        registers.AUX_MU_IER_REG.set(0);
+
+        registers
+            .AUX_MU_LCR_REG
+            .write(AUX_MU_LCR_REG::DATA_SIZE::EightBit);
+        registers.AUX_MU_MCR_REG.set(0);
+
+        // Discard anything left in the FIFOs
+        registers
+            .AUX_MU_IIR_REG
+            .write(AUX_MU_IIR_REG::CLEAR_RX::SET + AUX_MU_IIR_REG::CLEAR_TX::SET);
+
+        // baud rate = core clock / (8 * (divisor + 1)), rounded to the nearest divisor
+        let divisor = (core_clock as u64 + 4 * baud_rate as u64) / (8 * baud_rate as u64);
+        registers
+            .AUX_MU_BAUD_REG
+            .write(AUX_MU_BAUD_REG::BAUDRATE.val(divisor.clamp(1, 1 << 16) as u32 - 1));
+
+        // Enable the receive interrupt, whilst the transmit interrupt is only enabled when needed
+        registers.AUX_MU_IER_REG.write(AUX_MU_IER_REG::RX::SET);
+
+        registers
+            .AUX_MU_CNTL_REG
+            .write(AUX_MU_CNTL_REG::RX_ENABLE::SET + AUX_MU_CNTL_REG::TX_ENABLE::SET);
+
+        MiniUart {
+            _init_state: PhantomData,
+            receive_errors: self.receive_errors,
+        }
+    }
+}
+
+impl<const BASE_ADDRESS: usize> MiniUart<BASE_ADDRESS, Initialised> {
+    /// Create another handle to the mini UART, once it has already been initialised. This allows it
+    /// to be written to when the original handle is unavailable, such as whilst panicking.
+    ///
+    /// # Safety
+    ///
+    /// The peripheral must have been initialised with [`MiniUart::initialise`]. Anything written
+    /// through each handle may be interleaved if they are used at the same time.
+    pub unsafe fn steal() -> Self {
+        MiniUart {
+            _init_state: PhantomData,
+            receive_errors: ReceiveErrors::default(),
+        }
+    }
+
+    /// Write a single byte, blocking until there is space for it in the transmit FIFO.
+    fn write_byte(&mut self, byte: u8) {
+        let registers = unsafe { self.registers() };
+
+        while !registers.AUX_MU_LSR_REG.is_set(AUX_MU_LSR_REG::TX_EMPTY) {
+            core::hint::spin_loop();
+        }
+
+        registers.AUX_MU_IO_REG.set(byte as u32);
+    }
+}
+
+impl<const BASE_ADDRESS: usize> Uart for MiniUart<BASE_ADDRESS, Initialised> {
+    fn handle_interrupt<const RX: usize, const TX: usize>(
+        &mut self,
+        rx: &mut Producer<'_, RX>,
+        tx: &mut Consumer<'_, TX>,
+    ) {
+        let registers = unsafe { self.registers() };
+
+        if registers.AUX_MU_IER_REG.is_set(AUX_MU_IER_REG::TX) {
+            self.transmit(tx);
+        }
+
+        loop {
+            // Reading the status clears the overrun flag
+            let status = registers.AUX_MU_LSR_REG.extract();
+
+            if status.is_set(AUX_MU_LSR_REG::RX_OVERRUN) {
+                // The characters already in the FIFO are still valid, but later ones were lost
+                self.receive_errors.overrun += 1;
+            }
+
+            if !status.is_set(AUX_MU_LSR_REG::DATA_READY) {
+                break;
+            }
+
+            let byte = registers.AUX_MU_IO_REG.read(AUX_MU_IO_REG::DATA) as u8;
+
+            if rx.push(byte).is_err() {
+                self.receive_errors.dropped += 1;
+            }
+        }
+    }
+
+    fn receive_errors(&self) -> ReceiveErrors {
+        self.receive_errors
+    }
+
+    fn transmit<const N: usize>(&mut self, tx: &mut Consumer<'_, N>) {
+        let registers = unsafe { self.registers() };
+
+        while registers.AUX_MU_LSR_REG.is_set(AUX_MU_LSR_REG::TX_EMPTY) {
+            let Some(byte) = tx.pop() else {
+                // Nothing left to send, so there's no need to be notified when the FIFO drains
+                registers.AUX_MU_IER_REG.modify(AUX_MU_IER_REG::TX::CLEAR);
+
+                return;
+            };
+
+            registers.AUX_MU_IO_REG.set(byte as u32);
+        }
+
+        // The FIFO is full, so the interrupt will fire once it has drained
+        registers.AUX_MU_IER_REG.modify(AUX_MU_IER_REG::TX::SET);
+    }
+
+    fn transmit_blocking<const N: usize>(&mut self, tx: &mut Consumer<'_, N>) {
+        while let Some(byte) = tx.pop() {
+            self.write_byte(byte);
+        }
+    }
+
+    fn flush(&self) {
+        let registers = unsafe { self.registers() };
+
+        while !registers.AUX_MU_LSR_REG.is_set(AUX_MU_LSR_REG::TX_IDLE) {
+            core::hint::spin_loop();
+        }
+    }
+}
+
+/// Synchronously writes directly to the transmit FIFO, blocking whenever it is full.
+impl<const BASE_ADDRESS: usize> fmt::Write for MiniUart<BASE_ADDRESS, Initialised> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            self.write_byte(byte);
+        }
+
+        Ok(())
+    }
+}
+
+register_bitfields! {
+    u32,
+
+    /// Auxiliary Enables
+    AUX_ENABLES [
+        /// Mini UART enable. Its registers can't be accessed whilst it is disabled.
+        MINI_UART OFFSET(0) NUMBITS(1) [],
+    ],
+
+    /// Mini UART I/O Data
+    AUX_MU_IO_REG [
+        /// Byte to transmit when written, or the next received byte when read
+        DATA OFFSET(0) NUMBITS(8) [],
+    ],
+
+    /// Mini UART Interrupt Enable. The document swaps these bits.
+    AUX_MU_IER_REG [
+        /// Interrupt whilst the transmit FIFO is empty
+        TX OFFSET(1) NUMBITS(1) [],
+        /// Interrupt whilst the receive FIFO holds at least one byte
+        RX OFFSET(0) NUMBITS(1) [],
+    ],
+
+    /// Mini UART Interrupt Identify
+    AUX_MU_IIR_REG [
+        /// Writing a `1` clears the transmit FIFO
+        CLEAR_TX OFFSET(2) NUMBITS(1) [],
+        /// Writing a `1` clears the receive FIFO
+        CLEAR_RX OFFSET(1) NUMBITS(1) [],
+    ],
+
+    /// Mini UART Line Control
+    AUX_MU_LCR_REG [
+        /// Data size. The document only describes bit 0, but both bits must be set for 8 bits.
+        DATA_SIZE OFFSET(0) NUMBITS(2) [
+            SevenBit = 0b00,
+            EightBit = 0b11,
+        ],
+    ],
+
+    /// Mini UART Line Status
+    AUX_MU_LSR_REG [
+        /// The transmit FIFO is empty, and the transmitter is idle
+        TX_IDLE OFFSET(6) NUMBITS(1) [],
+        /// The transmit FIFO can accept at least one byte
+        TX_EMPTY OFFSET(5) NUMBITS(1) [],
+        /// A byte was received whilst the receive FIFO was full. Cleared when read.
+        RX_OVERRUN OFFSET(1) NUMBITS(1) [],
+        /// The receive FIFO holds at least one byte
+        DATA_READY OFFSET(0) NUMBITS(1) [],
+    ],
+
+    /// Mini UART Extra Control
+    AUX_MU_CNTL_REG [
+        /// Transmitter enable
+        TX_ENABLE OFFSET(1) NUMBITS(1) [],
+        /// Receiver enable
+        RX_ENABLE OFFSET(0) NUMBITS(1) [],
+    ],
+
+    /// Mini UART Baudrate
+    AUX_MU_BAUD_REG [
+        BAUDRATE OFFSET(0) NUMBITS(16) [],
+    ],
+}
+
+register_structs! {
+    #[allow(non_snake_case)]
+    RegisterBlock {
+        (0x00 => AUX_IRQ: ReadOnly<u32>),
+        (0x04 => AUX_ENABLES: ReadWrite<u32, AUX_ENABLES::Register>),
+        (0x08 => _reserved),
+        (0x40 => AUX_MU_IO_REG: ReadWrite<u32, AUX_MU_IO_REG::Register>),
+        (0x44 => AUX_MU_IER_REG: ReadWrite<u32, AUX_MU_IER_REG::Register>),
+        (0x48 => AUX_MU_IIR_REG: ReadWrite<u32, AUX_MU_IIR_REG::Register>),
+        (0x4C => AUX_MU_LCR_REG: ReadWrite<u32, AUX_MU_LCR_REG::Register>),
+        (0x50 => AUX_MU_MCR_REG: ReadWrite<u32>),
+        (0x54 => AUX_MU_LSR_REG: ReadOnly<u32, AUX_MU_LSR_REG::Register>),
+        (0x58 => AUX_MU_MSR_REG: ReadOnly<u32>),
+        (0x5C => AUX_MU_SCRATCH: ReadWrite<u32>),
+        (0x60 => AUX_MU_CNTL_REG: ReadWrite<u32, AUX_MU_CNTL_REG::Register>),
+        (0x64 => AUX_MU_STAT_REG: ReadOnly<u32>),
+        (0x68 => AUX_MU_BAUD_REG: ReadWrite<u32, AUX_MU_BAUD_REG::Register>),
+        (0x6C => @END),
+    }
+}
@@ -0,0 +1,519 @@
+//! Driver for the GPIO controller of the BCM2835, which controls the function, level and pull of
+//! each of the 54 pins of the chip.
+//!
+//! Each pin is claimed from [`Gpio`] as a [`Pin`], so only one owner can reconfigure it. The type
+//! of the pin records the function it was configured for, so only inputs can be read for events
+//! and only outputs can be driven. Other than the set and clear registers, every register is
+//! shared by several pins, so changes to them are serialised by the controller.
+//!
+//! _(reference: BCM2835 ARM Peripherals, section 6)_
+
+#![no_std]
+
+use core::{
+    convert::Infallible,
+    marker::PhantomData,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use embedded_hal::delay::DelayNs;
+use lib_kernel::{sync::IrqSpinMutex, Arch};
+use tock_registers::{interfaces::*, register_bitfields, register_structs, registers::*};
+
+/// Number of pins on the chip.
+pub const PIN_COUNT: usize = 54;
+
+/// Number of pins covered by each function select register.
+const PINS_PER_FUNCTION_SELECT: usize = 10;
+
+/// Number of pins covered by each of the other registers.
+const PINS_PER_REGISTER: usize = 32;
+
+/// Function of a pin, selecting between a plain input or output, or one of the alternate functions
+/// (such as a UART or SPI) that are connected to it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Function {
+    Input,
+    Output,
+    Alt0,
+    Alt1,
+    Alt2,
+    Alt3,
+    Alt4,
+    Alt5,
+}
+
+impl Function {
+    /// Value of the function in a function select register. The alternate functions aren't in
+    /// numerical order.
+    const fn bits(self) -> u32 {
+        match self {
+            Function::Input => 0b000,
+            Function::Output => 0b001,
+            Function::Alt0 => 0b100,
+            Function::Alt1 => 0b101,
+            Function::Alt2 => 0b110,
+            Function::Alt3 => 0b111,
+            Function::Alt4 => 0b011,
+            Function::Alt5 => 0b010,
+        }
+    }
+
+    const fn from_bits(bits: u32) -> Self {
+        match bits & 0b111 {
+            0b000 => Function::Input,
+            0b001 => Function::Output,
+            0b100 => Function::Alt0,
+            0b101 => Function::Alt1,
+            0b110 => Function::Alt2,
+            0b111 => Function::Alt3,
+            0b011 => Function::Alt4,
+            _ => Function::Alt5,
+        }
+    }
+}
+
+/// Internal resistor applied to a pin.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pull {
+    None,
+    Down,
+    Up,
+}
+
+/// Condition on an input which sets its event detect status.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Detect {
+    /// A low to high transition, sampled against the system clock.
+    RisingEdge,
+    /// A high to low transition, sampled against the system clock.
+    FallingEdge,
+    /// A low to high transition, without sampling, so very short pulses are also detected.
+    AsyncRisingEdge,
+    /// A high to low transition, without sampling, so very short pulses are also detected.
+    AsyncFallingEdge,
+    /// The input being high. The status is set again as soon as it is cleared, until the input
+    /// goes low.
+    High,
+    /// The input being low. The status is set again as soon as it is cleared, until the input
+    /// goes high.
+    Low,
+}
+
+/// Pin which has not been configured since it was claimed, so has the function that it was left
+/// with by the firmware or a previous owner.
+pub enum Unconfigured {}
+/// Pin configured as an input.
+pub enum Input {}
+/// Pin configured as an output.
+pub enum Output {}
+/// Pin configured for one of its alternate functions.
+pub enum Alternate {}
+
+/// The GPIO controller.
+pub struct Gpio<const BASE_ADDRESS: usize, A> {
+    /// Bit set for each pin which is currently claimed.
+    claimed: AtomicU64,
+    /// Serialises read-modify-write accesses to registers which are shared between pins. IRQs are
+    /// masked whilst it is held, as inputs may be handled from interrupt handlers.
+    lock: IrqSpinMutex<A, ()>,
+}
+
+impl<const BASE_ADDRESS: usize, A: Arch> Gpio<BASE_ADDRESS, A> {
+    /// Create a new instance of the controller, with no pins claimed.
+    pub const fn new() -> Self {
+        Self {
+            claimed: AtomicU64::new(0),
+            lock: IrqSpinMutex::new(()),
+        }
+    }
+
+    /// Fetch the register block of this instance.
+    ///
+    /// # Safety
+    ///
+    /// `BASE_ADDRESS` must be a valid memory address, and point to the start of the memory-mapped
+    /// registers of the GPIO controller.
+    unsafe fn registers(&self) -> &'static RegisterBlock {
+        &*(BASE_ADDRESS as *const RegisterBlock)
+    }
+
+    /// Claim pin `number`, or return `None` if it doesn't exist or is already claimed.
+    pub fn pin(&self, number: usize) -> Option<Pin<'_, BASE_ADDRESS, A, Unconfigured>> {
+        if number >= PIN_COUNT {
+            return None;
+        }
+
+        let bit = 1 << number;
+
+        if self.claimed.fetch_or(bit, Ordering::Acquire) & bit != 0 {
+            return None;
+        }
+
+        Some(Pin {
+            gpio: self,
+            number,
+            _mode: PhantomData,
+        })
+    }
+
+    /// Recreate a pin which was claimed and configured for `Mode`, then dropped.
+    ///
+    /// # Safety
+    ///
+    /// Pin `number` must exist, be claimed by the caller, and be configured for `Mode`. No other
+    /// [`Pin`] may exist for it.
+    pub unsafe fn steal<Mode>(&self, number: usize) -> Pin<'_, BASE_ADDRESS, A, Mode> {
+        Pin {
+            gpio: self,
+            number,
+            _mode: PhantomData,
+        }
+    }
+
+    /// Event detect status of every pin, with bit `n` set if pin `n` has detected an event.
+    pub fn events(&self) -> u64 {
+        let registers = unsafe { self.registers() };
+
+        registers.GPEDS[0].get() as u64 | (registers.GPEDS[1].get() as u64) << PINS_PER_REGISTER
+    }
+
+    /// Clear the event detect status of every pin with its bit set in `pins`. This is intended
+    /// for the interrupt handler of the board, which may need to acknowledge events on pins that
+    /// it doesn't hold.
+    pub fn clear_events(&self, pins: u64) {
+        let registers = unsafe { self.registers() };
+
+        // Writing a `1` clears the status, whilst a `0` has no effect
+        registers.GPEDS[0].set(pins as u32);
+        registers.GPEDS[1].set((pins >> PINS_PER_REGISTER) as u32);
+    }
+
+    /// Current function of pin `number`, whether or not it is claimed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `number` is not a pin on the chip.
+    pub fn function(&self, number: usize) -> Function {
+        assert!(number < PIN_COUNT, "GPIO pin {number} does not exist");
+
+        let registers = unsafe { self.registers() };
+        let (index, shift) = function_position(number);
+
+        Function::from_bits(registers.GPFSEL[index].get() >> shift)
+    }
+
+    /// Current level of pin `number`, whether or not it is claimed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `number` is not a pin on the chip.
+    pub fn is_high(&self, number: usize) -> bool {
+        assert!(number < PIN_COUNT, "GPIO pin {number} does not exist");
+
+        let registers = unsafe { self.registers() };
+        let (index, bit) = position(number);
+
+        registers.GPLEV[index].get() & bit != 0
+    }
+}
+
+impl<const BASE_ADDRESS: usize, A: Arch> Default for Gpio<BASE_ADDRESS, A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A claimed pin, configured for the function given by `Mode`.
+///
+/// Dropping a pin leaves it configured as it was, and claimed so that it can't be reconfigured by
+/// anything else. It must be [released](Pin::release) to be claimed again.
+pub struct Pin<'a, const BASE_ADDRESS: usize, A, Mode> {
+    gpio: &'a Gpio<BASE_ADDRESS, A>,
+    number: usize,
+    _mode: PhantomData<Mode>,
+}
+
+impl<'a, const BASE_ADDRESS: usize, A: Arch, Mode> Pin<'a, BASE_ADDRESS, A, Mode> {
+    /// Number of the pin on the chip.
+    pub fn number(&self) -> usize {
+        self.number
+    }
+
+    /// Current level of the pin, which for an output is the level it is driven to.
+    pub fn is_high(&self) -> bool {
+        self.gpio.is_high(self.number)
+    }
+
+    /// Current function of the pin.
+    pub fn function(&self) -> Function {
+        self.gpio.function(self.number)
+    }
+
+    /// Apply an internal pull resistor to the pin, or remove it. The resistor remains applied
+    /// through a reset, but its state can't be read back.
+    ///
+    /// `delay` is used to wait for the control signal to settle.
+    pub fn set_pull(&mut self, pull: Pull, delay: &mut impl DelayNs) {
+        let registers = unsafe { self.gpio.registers() };
+        let (index, bit) = position(self.number);
+
+        let value = match pull {
+            Pull::None => GPPUD::PUD::Off,
+            Pull::Down => GPPUD::PUD::PullDown,
+            Pull::Up => GPPUD::PUD::PullUp,
+        };
+
+        // The control signal is shared by all pins, so only one pin may be changed at once.
+        // Each step must be held for at least 150 cycles of the peripheral clock.
+        let _guard = self.gpio.lock.lock();
+
+        registers.GPPUD.write(value);
+        delay.delay_us(1);
+        registers.GPPUDCLK[index].set(bit);
+        delay.delay_us(1);
+        registers.GPPUD.write(GPPUD::PUD::Off);
+        registers.GPPUDCLK[index].set(0);
+    }
+
+    /// Configure the pin as an input.
+    pub fn into_input(self) -> Pin<'a, BASE_ADDRESS, A, Input> {
+        self.into_function(Function::Input)
+    }
+
+    /// Configure the pin as an output, initially driven to `high`.
+    pub fn into_output(self, high: bool) -> Pin<'a, BASE_ADDRESS, A, Output> {
+        // Set the level first, so the pin doesn't glitch when it starts being driven
+        self.write(high);
+
+        self.into_function(Function::Output)
+    }
+
+    /// Configure the pin for one of its alternate functions.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `function` is not one of the alternate functions.
+    pub fn into_alternate(self, function: Function) -> Pin<'a, BASE_ADDRESS, A, Alternate> {
+        assert!(
+            !matches!(function, Function::Input | Function::Output),
+            "{function:?} is not an alternate function"
+        );
+
+        self.into_function(function)
+    }
+
+    /// Return the pin to the controller, leaving it configured as it is, so it can be claimed
+    /// again.
+    pub fn release(self) {
+        self.gpio
+            .claimed
+            .fetch_and(!(1 << self.number), Ordering::Release);
+    }
+
+    fn into_function<M>(self, function: Function) -> Pin<'a, BASE_ADDRESS, A, M> {
+        let register = &unsafe { self.gpio.registers() }.GPFSEL;
+        let (index, shift) = function_position(self.number);
+
+        {
+            let _guard = self.gpio.lock.lock();
+            let value = register[index].get() & !(0b111 << shift);
+
+            register[index].set(value | function.bits() << shift);
+        }
+
+        Pin {
+            gpio: self.gpio,
+            number: self.number,
+            _mode: PhantomData,
+        }
+    }
+
+    /// Set the level that the pin is driven to whilst it is an output.
+    fn write(&self, high: bool) {
+        let registers = unsafe { self.gpio.registers() };
+        let (index, bit) = position(self.number);
+
+        // Writing a `0` bit has no effect, so other pins are untouched
+        if high {
+            registers.GPSET[index].set(bit);
+        } else {
+            registers.GPCLR[index].set(bit);
+        }
+    }
+}
+
+impl<const BASE_ADDRESS: usize, A: Arch> Pin<'_, BASE_ADDRESS, A, Input> {
+    /// Start setting the event detect status of the pin when `detect` occurs. Several conditions
+    /// may be enabled at once.
+    pub fn enable_detect(&mut self, detect: Detect) {
+        self.modify_detect(detect, true);
+    }
+
+    /// Stop setting the event detect status of the pin when `detect` occurs.
+    pub fn disable_detect(&mut self, detect: Detect) {
+        self.modify_detect(detect, false);
+    }
+
+    /// Whether one of the enabled conditions has occurred since the status was last cleared.
+    pub fn event_detected(&self) -> bool {
+        let registers = unsafe { self.gpio.registers() };
+        let (index, bit) = position(self.number);
+
+        registers.GPEDS[index].get() & bit != 0
+    }
+
+    /// Clear the event detect status of the pin.
+    pub fn clear_event(&mut self) {
+        let registers = unsafe { self.gpio.registers() };
+        let (index, bit) = position(self.number);
+
+        // Writing a `1` clears the status, whilst a `0` has no effect
+        registers.GPEDS[index].set(bit);
+    }
+
+    fn modify_detect(&mut self, detect: Detect, enable: bool) {
+        let registers = unsafe { self.gpio.registers() };
+
+        let register = match detect {
+            Detect::RisingEdge => &registers.GPREN,
+            Detect::FallingEdge => &registers.GPFEN,
+            Detect::AsyncRisingEdge => &registers.GPAREN,
+            Detect::AsyncFallingEdge => &registers.GPAFEN,
+            Detect::High => &registers.GPHEN,
+            Detect::Low => &registers.GPLEN,
+        };
+
+        let (index, bit) = position(self.number);
+        let _guard = self.gpio.lock.lock();
+
+        if enable {
+            register[index].set(register[index].get() | bit);
+        } else {
+            register[index].set(register[index].get() & !bit);
+        }
+    }
+}
+
+impl<const BASE_ADDRESS: usize, A: Arch> Pin<'_, BASE_ADDRESS, A, Output> {
+    /// Drive the pin high.
+    pub fn set_high(&mut self) {
+        self.write(true);
+    }
+
+    /// Drive the pin low.
+    pub fn set_low(&mut self) {
+        self.write(false);
+    }
+
+    /// Drive the pin to the opposite level.
+    pub fn toggle(&mut self) {
+        self.write(!self.is_high());
+    }
+}
+
+impl<const BASE_ADDRESS: usize, A: Arch, Mode> embedded_hal::digital::ErrorType
+    for Pin<'_, BASE_ADDRESS, A, Mode>
+{
+    type Error = Infallible;
+}
+
+impl<const BASE_ADDRESS: usize, A: Arch> embedded_hal::digital::InputPin
+    for Pin<'_, BASE_ADDRESS, A, Input>
+{
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(Pin::is_high(self))
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(!Pin::is_high(self))
+    }
+}
+
+impl<const BASE_ADDRESS: usize, A: Arch> embedded_hal::digital::OutputPin
+    for Pin<'_, BASE_ADDRESS, A, Output>
+{
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        Pin::set_low(self);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Pin::set_high(self);
+        Ok(())
+    }
+}
+
+impl<const BASE_ADDRESS: usize, A: Arch> embedded_hal::digital::StatefulOutputPin
+    for Pin<'_, BASE_ADDRESS, A, Output>
+{
+    fn is_set_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(Pin::is_high(self))
+    }
+
+    fn is_set_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(!Pin::is_high(self))
+    }
+}
+
+/// Split a pin number into the index of its function select register, and the shift of its field
+/// within it.
+const fn function_position(number: usize) -> (usize, u32) {
+    (
+        number / PINS_PER_FUNCTION_SELECT,
+        (number % PINS_PER_FUNCTION_SELECT) as u32 * 3,
+    )
+}
+
+/// Split a pin number into the register index and bit that represent it.
+const fn position(number: usize) -> (usize, u32) {
+    (
+        number / PINS_PER_REGISTER,
+        1 << (number % PINS_PER_REGISTER),
+    )
+}
+
+register_bitfields! {
+    u32,
+
+    /// GPIO Pull-up/down
+    GPPUD [
+        /// Resistor applied to the pins that are clocked by `GPPUDCLK`
+        PUD OFFSET(0) NUMBITS(2) [
+            Off = 0b00,
+            PullDown = 0b01,
+            PullUp = 0b10,
+        ],
+    ],
+}
+
+register_structs! {
+    #[allow(non_snake_case)]
+    RegisterBlock {
+        (0x00 => GPFSEL: [ReadWrite<u32>; 6]),
+        (0x18 => _reserved0),
+        (0x1C => GPSET: [WriteOnly<u32>; 2]),
+        (0x24 => _reserved1),
+        (0x28 => GPCLR: [WriteOnly<u32>; 2]),
+        (0x30 => _reserved2),
+        (0x34 => GPLEV: [ReadOnly<u32>; 2]),
+        (0x3C => _reserved3),
+        (0x40 => GPEDS: [ReadWrite<u32>; 2]),
+        (0x48 => _reserved4),
+        (0x4C => GPREN: [ReadWrite<u32>; 2]),
+        (0x54 => _reserved5),
+        (0x58 => GPFEN: [ReadWrite<u32>; 2]),
+        (0x60 => _reserved6),
+        (0x64 => GPHEN: [ReadWrite<u32>; 2]),
+        (0x6C => _reserved7),
+        (0x70 => GPLEN: [ReadWrite<u32>; 2]),
+        (0x78 => _reserved8),
+        (0x7C => GPAREN: [ReadWrite<u32>; 2]),
+        (0x84 => _reserved9),
+        (0x88 => GPAFEN: [ReadWrite<u32>; 2]),
+        (0x90 => _reserved10),
+        (0x94 => GPPUD: ReadWrite<u32, GPPUD::Register>),
+        (0x98 => GPPUDCLK: [ReadWrite<u32>; 2]),
+        (0xA0 => @END),
+    }
+}
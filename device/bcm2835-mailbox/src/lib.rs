@@ -0,0 +1,216 @@
+//! Driver for the mailbox between the ARM cores and the VideoCore, used to query and control the
+//! board through the firmware's property interface.
+//!
+//! Each request is a buffer of property tags in memory, whose address is written to the mailbox.
+//! The firmware replaces the value of each tag with its response, then replies through the other
+//! mailbox. The VideoCore doesn't see the caches of the cores, so the buffer is cleaned from the
+//! data cache before it is sent, and invalidated once the response arrives.
+//!
+//! The mailboxes aren't covered by the BCM2835 ARM Peripherals document, so these details match
+//! the firmware's documentation of the property interface, and the driver in Linux
+//! (`drivers/mailbox/bcm2835-mailbox.c`).
+
+#![no_std]
+
+pub mod tags;
+
+use core::{mem::size_of, ptr};
+
+use lib_kernel::{sync::IrqSpinMutex, Arch};
+use tock_registers::{interfaces::*, register_bitfields, register_structs, registers::*};
+
+/// Channel of the property interface, for requests from the ARM cores to the VideoCore.
+const PROPERTY_CHANNEL: u32 = 8;
+
+/// Alias through which the VideoCore accesses memory of the ARM cores without its own L2 cache.
+const BUS_ADDRESS_ALIAS: u32 = 0xC000_0000;
+
+/// Number of words in the request buffer, which is enough for any single tag.
+const BUFFER_WORDS: usize = 32;
+
+/// Number of words in a buffer holding one tag, other than its value.
+const OVERHEAD_WORDS: usize = 6;
+
+/// Code of a buffer which is a request.
+const REQUEST: u32 = 0;
+/// Code of a buffer which the firmware handled successfully.
+const RESPONSE_SUCCESS: u32 = 0x8000_0000;
+/// Bit set in the value length of a tag which the firmware has responded to.
+const TAG_RESPONSE: u32 = 1 << 31;
+/// Tag marking the end of a buffer.
+const END_TAG: u32 = 0;
+
+/// A property tag, which gets or sets a single value of the firmware.
+///
+/// # Safety
+///
+/// `Request` and `Response` must be laid out as the firmware expects the value of the tag, and
+/// every bit pattern must be a valid `Response`.
+pub unsafe trait Tag {
+    /// Identifier of the tag.
+    const ID: u32;
+
+    /// Value sent to the firmware.
+    type Request: Copy;
+
+    /// Value returned by the firmware.
+    type Response: Copy;
+}
+
+/// Reason that a request to the firmware failed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MailboxError {
+    /// The firmware couldn't parse the request.
+    Failed,
+    /// The firmware doesn't recognise the tag, or rejected its value.
+    Unsupported,
+    /// The firmware returned a shorter value than expected.
+    Truncated,
+}
+
+/// Buffer holding a request, which must be 16 byte aligned as its address shares the mailbox
+/// register with the channel.
+#[repr(C, align(16))]
+struct Buffer([u32; BUFFER_WORDS]);
+
+/// The mailboxes between the ARM cores and the VideoCore.
+pub struct Mailbox<const BASE_ADDRESS: usize, A> {
+    /// Buffer for requests, which are serialised by its lock. IRQs are masked whilst it is held,
+    /// so a request can't be left waiting for a response by the thread being switched out.
+    buffer: IrqSpinMutex<A, Buffer>,
+}
+
+impl<const BASE_ADDRESS: usize, A: Arch> Mailbox<BASE_ADDRESS, A> {
+    /// Create a new instance of the mailbox.
+    pub const fn new() -> Self {
+        Self {
+            buffer: IrqSpinMutex::new(Buffer([0; BUFFER_WORDS])),
+        }
+    }
+
+    /// Fetch the register block of this instance.
+    ///
+    /// # Safety
+    ///
+    /// `BASE_ADDRESS` must be a valid memory address, and point to the start of the memory-mapped
+    /// registers of the mailboxes.
+    unsafe fn registers(&self) -> &'static RegisterBlock {
+        &*(BASE_ADDRESS as *const RegisterBlock)
+    }
+
+    /// Send `request` for the tag `T` to the firmware, waiting for its response.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value of `T` doesn't fit in the request buffer.
+    pub fn request<T: Tag>(&self, request: T::Request) -> Result<T::Response, MailboxError> {
+        let value_size = size_of::<T::Request>()
+            .max(size_of::<T::Response>())
+            .next_multiple_of(4);
+        let value_words = value_size / 4;
+
+        assert!(
+            OVERHEAD_WORDS + value_words <= BUFFER_WORDS,
+            "value of tag {:#x} is too large for the mailbox buffer",
+            T::ID
+        );
+
+        let mut buffer = self.buffer.lock();
+        let words = &mut buffer.0;
+
+        words.fill(0);
+        words[0] = ((OVERHEAD_WORDS + value_words) * 4) as u32;
+        words[1] = REQUEST;
+        words[2] = T::ID;
+        words[3] = value_size as u32;
+        words[4] = REQUEST;
+
+        // Safety: The value fits in the buffer, as checked above.
+        unsafe { ptr::write_unaligned(words[5..].as_mut_ptr().cast(), request) };
+
+        words[5 + value_words] = END_TAG;
+
+        self.call(&mut buffer);
+
+        // The firmware wrote the response behind the compiler's back, so it must be re-read
+        // Safety: The buffer is valid for reads, and is not being written by the firmware.
+        let words = unsafe { ptr::read_volatile(&buffer.0) };
+
+        if words[1] != RESPONSE_SUCCESS {
+            return Err(MailboxError::Failed);
+        }
+
+        if words[4] & TAG_RESPONSE == 0 {
+            return Err(MailboxError::Unsupported);
+        }
+
+        if ((words[4] & !TAG_RESPONSE) as usize) < size_of::<T::Response>() {
+            return Err(MailboxError::Truncated);
+        }
+
+        // Safety: Any bit pattern is a valid response, as required by `Tag`.
+        Ok(unsafe { ptr::read_unaligned(words[5..].as_ptr().cast()) })
+    }
+
+    /// Send the buffer to the firmware on the property channel, and wait for its response.
+    fn call(&self, buffer: &mut Buffer) {
+        let registers = unsafe { self.registers() };
+        let address = buffer as *mut Buffer as usize;
+
+        A::clean_dcache(address, size_of::<Buffer>());
+
+        while registers.STATUS1.is_set(STATUS::FULL) {
+            core::hint::spin_loop();
+        }
+
+        let bus_address = address as u32 | BUS_ADDRESS_ALIAS;
+        registers.WRITE1.set(bus_address | PROPERTY_CHANNEL);
+
+        loop {
+            while registers.STATUS0.is_set(STATUS::EMPTY) {
+                core::hint::spin_loop();
+            }
+
+            // Responses on other channels aren't for this request, so are discarded
+            if registers.READ0.get() == bus_address | PROPERTY_CHANNEL {
+                break;
+            }
+        }
+
+        A::invalidate_dcache(address, size_of::<Buffer>());
+    }
+}
+
+impl<const BASE_ADDRESS: usize, A: Arch> Default for Mailbox<BASE_ADDRESS, A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+register_bitfields! {
+    u32,
+
+    /// Mailbox Status
+    STATUS [
+        /// No more messages can be written
+        FULL OFFSET(31) NUMBITS(1) [],
+        /// No messages are waiting to be read
+        EMPTY OFFSET(30) NUMBITS(1) [],
+    ],
+}
+
+register_structs! {
+    #[allow(non_snake_case)]
+    RegisterBlock {
+        /// Mailbox 0 is written by the VideoCore, and read by the ARM cores
+        (0x00 => READ0: ReadOnly<u32>),
+        (0x04 => _reserved0),
+        (0x18 => STATUS0: ReadOnly<u32, STATUS::Register>),
+        (0x1C => _reserved1),
+        /// Mailbox 1 is written by the ARM cores, and read by the VideoCore
+        (0x20 => WRITE1: WriteOnly<u32>),
+        (0x24 => _reserved2),
+        (0x38 => STATUS1: ReadOnly<u32, STATUS::Register>),
+        (0x3C => @END),
+    }
+}
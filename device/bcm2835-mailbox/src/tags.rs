@@ -0,0 +1,162 @@
+//! Property tags understood by the firmware, each with the layout of its request and response.
+
+use crate::Tag;
+
+/// Region of memory, as a start address and size in bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(C)]
+pub struct MemoryRegion {
+    pub base: u32,
+    pub size: u32,
+}
+
+/// Clock of the board, whose rate can be queried.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u32)]
+pub enum Clock {
+    Emmc = 1,
+    Uart = 2,
+    Arm = 3,
+    /// Clock of the VideoCore, which also drives the peripheral bus.
+    Core = 4,
+    V3d = 5,
+    H264 = 6,
+    Isp = 7,
+    Sdram = 8,
+    Pixel = 9,
+    Pwm = 10,
+}
+
+/// Rate of a clock, in Hz.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(C)]
+pub struct ClockRate {
+    /// The [`Clock`] that the rate is for.
+    pub clock: u32,
+    pub rate: u32,
+}
+
+/// Power domain of a device on the board, which the firmware can power on or off.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u32)]
+pub enum PowerDomain {
+    SdCard = 0,
+    Uart0 = 1,
+    Uart1 = 2,
+    UsbHcd = 3,
+    I2c0 = 4,
+    I2c1 = 5,
+    I2c2 = 6,
+    Spi = 7,
+    Ccp2tx = 8,
+}
+
+/// Request to change the power state of a domain.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(C)]
+pub struct PowerRequest {
+    pub domain: PowerDomain,
+    /// Combination of [`PowerState::ON`] and [`PowerState::WAIT`].
+    pub state: u32,
+}
+
+/// Power state of a domain.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(C)]
+pub struct PowerState {
+    /// The [`PowerDomain`] that the state is for.
+    pub domain: u32,
+    pub state: u32,
+}
+
+impl PowerState {
+    /// The domain is powered on.
+    pub const ON: u32 = 1 << 0;
+    /// When requesting a change, wait for the domain to become stable before responding.
+    pub const WAIT: u32 = 1 << 1;
+    /// The domain doesn't exist.
+    pub const MISSING: u32 = 1 << 1;
+
+    /// Whether the domain exists, and is powered on.
+    pub const fn is_on(&self) -> bool {
+        self.state & (Self::ON | Self::MISSING) == Self::ON
+    }
+}
+
+/// Temperature of the SoC, in thousandths of a degree Celsius.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(C)]
+pub struct Temperature {
+    /// Identifier of the sensor, which is always `0`.
+    pub sensor: u32,
+    pub millicelsius: u32,
+}
+
+/// Generate the type for a tag, and implement [`Tag`] for it.
+macro_rules! tag {
+    ($(#[$meta:meta])* $name:ident = $id:literal, $request:ty => $response:ty) => {
+        $(#[$meta])*
+        pub enum $name {}
+
+        // Safety: The request and response match the firmware's documentation of the tag, and
+        // every response is made up of integers, so is valid for any bit pattern.
+        unsafe impl Tag for $name {
+            const ID: u32 = $id;
+            type Request = $request;
+            type Response = $response;
+        }
+    };
+}
+
+tag!(
+    /// Revision of the VideoCore firmware.
+    FirmwareRevision = 0x0000_0001, () => u32
+);
+tag!(
+    /// Model of the board.
+    BoardModel = 0x0001_0001, () => u32
+);
+tag!(
+    /// Revision code of the board, which identifies its model, memory size and manufacturer.
+    BoardRevision = 0x0001_0002, () => u32
+);
+tag!(
+    /// MAC address of the on-board network interface.
+    MacAddress = 0x0001_0003, () => [u8; 6]
+);
+tag!(
+    /// Serial number of the board.
+    BoardSerial = 0x0001_0004, () => u64
+);
+tag!(
+    /// Memory which is available to the ARM cores.
+    ArmMemory = 0x0001_0005, () => MemoryRegion
+);
+tag!(
+    /// Memory which is reserved by the VideoCore.
+    VideoCoreMemory = 0x0001_0006, () => MemoryRegion
+);
+tag!(
+    /// Whether a power domain is on.
+    GetPowerState = 0x0002_0001, PowerDomain => PowerState
+);
+tag!(
+    /// Power a domain on or off.
+    SetPowerState = 0x0002_8001, PowerRequest => PowerState
+);
+tag!(
+    /// Current rate of a clock, or `0` if the clock doesn't exist.
+    GetClockRate = 0x0003_0002, Clock => ClockRate
+);
+tag!(
+    /// Highest rate that a clock may run at.
+    GetMaxClockRate = 0x0003_0004, Clock => ClockRate
+);
+tag!(
+    /// Current temperature of the SoC, for sensor `0`.
+    GetTemperature = 0x0003_0006, u32 => Temperature
+);
+tag!(
+    /// Temperature at which the SoC starts throttling, for sensor `0`.
+    GetMaxTemperature = 0x0003_000A, u32 => Temperature
+);
@@ -0,0 +1,218 @@
+//! Driver for the BCM2835 system timer, a free-running 64 bit counter at 1 MHz with four 32 bit
+//! compare channels.
+//!
+//! Channels 0 and 2 are used by the VideoCore firmware, so only channels 1 and 3 are available.
+//! Each channel raises its own interrupt when the lower 32 bits of the counter equal its compare
+//! value, which is routed through the board's interrupt controller.
+//!
+//! _(reference: BCM2835 ARM Peripherals, section 12)_
+
+#![no_std]
+
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use lib_kernel::time::{ClockEvent, ClockSource, DeadlinePassed};
+use tock_registers::{interfaces::*, register_structs, registers::*};
+
+/// Frequency of the counter, in Hz.
+pub const FREQUENCY: u64 = 1_000_000;
+
+/// The free-running counter of the system timer.
+pub struct SystemTimer<const BASE_ADDRESS: usize>;
+
+impl<const BASE_ADDRESS: usize> SystemTimer<BASE_ADDRESS> {
+    /// Create a new system timer instance. The counter always runs, so needs no initialisation.
+    pub const fn new() -> Self {
+        Self
+    }
+
+    /// Current value of the 64 bit counter.
+    pub fn counter(&self) -> u64 {
+        counter(unsafe { registers::<BASE_ADDRESS>() })
+    }
+}
+
+impl<const BASE_ADDRESS: usize> Default for SystemTimer<BASE_ADDRESS> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The counter is shared by all cores, but each read is a slow access to the peripheral bus, and
+/// its resolution is only a microsecond.
+impl<const BASE_ADDRESS: usize> ClockSource for SystemTimer<BASE_ADDRESS> {
+    fn name(&self) -> &'static str {
+        "BCM2835 system timer"
+    }
+
+    fn rating(&self) -> u32 {
+        200
+    }
+
+    fn frequency(&self) -> u64 {
+        FREQUENCY
+    }
+
+    fn read(&self) -> u64 {
+        self.counter()
+    }
+}
+
+/// A compare channel of the system timer, which raises an interrupt at a deadline.
+///
+/// As the channel only compares the lower 32 bits of the counter, deadlines more than about 71
+/// minutes away are reached in several steps. The interrupt handler of the board must call
+/// [`CompareChannel::take_match`] to find whether the deadline has really been reached.
+pub struct CompareChannel<const BASE_ADDRESS: usize, const CHANNEL: usize> {
+    /// Whether a deadline is set, so that matches after it is cancelled can be ignored.
+    armed: AtomicBool,
+    /// Full 64 bit deadline of the channel.
+    deadline: AtomicU64,
+}
+
+impl<const BASE_ADDRESS: usize, const CHANNEL: usize> CompareChannel<BASE_ADDRESS, CHANNEL> {
+    /// Create a new instance of the channel, with no deadline set.
+    pub const fn new() -> Self {
+        const {
+            assert!(
+                CHANNEL == 1 || CHANNEL == 3,
+                "channels 0 and 2 are used by the VideoCore"
+            );
+        }
+
+        Self {
+            armed: AtomicBool::new(false),
+            deadline: AtomicU64::new(0),
+        }
+    }
+
+    /// Set the compare value to the lower 32 bits of `deadline`.
+    fn compare(&self, deadline: u64) {
+        let registers = unsafe { registers::<BASE_ADDRESS>() };
+
+        // Clear any earlier match, so that it can't be mistaken for this deadline
+        registers.CS.set(1 << CHANNEL);
+        registers.C[CHANNEL].set(deadline as u32);
+    }
+
+    /// Acknowledge a match on this channel, returning whether the deadline has been reached. If
+    /// the counter only matched the lower 32 bits of a later deadline, the channel is re-armed.
+    ///
+    /// Must be called whenever the interrupt of the channel is pending.
+    pub fn take_match(&self) -> bool {
+        let registers = unsafe { registers::<BASE_ADDRESS>() };
+
+        if registers.CS.get() & 1 << CHANNEL == 0 {
+            return false;
+        }
+
+        registers.CS.set(1 << CHANNEL);
+
+        if !self.armed.load(Ordering::Acquire) {
+            return false;
+        }
+
+        let deadline = self.deadline.load(Ordering::Relaxed);
+
+        if counter(registers) < deadline {
+            // The compare value is still correct, so the next match is 2^32 ticks later
+            return false;
+        }
+
+        self.armed.swap(false, Ordering::AcqRel)
+    }
+}
+
+impl<const BASE_ADDRESS: usize, const CHANNEL: usize> Default
+    for CompareChannel<BASE_ADDRESS, CHANNEL>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The channel is shared by all cores. Matches are only detected on an exact comparison, so a
+/// deadline that has already passed is reported rather than firing immediately.
+impl<const BASE_ADDRESS: usize, const CHANNEL: usize> ClockEvent
+    for CompareChannel<BASE_ADDRESS, CHANNEL>
+{
+    fn name(&self) -> &'static str {
+        "BCM2835 system timer"
+    }
+
+    fn rating(&self) -> u32 {
+        100
+    }
+
+    fn per_core(&self) -> bool {
+        false
+    }
+
+    fn frequency(&self) -> u64 {
+        FREQUENCY
+    }
+
+    fn counter(&self) -> u64 {
+        counter(unsafe { registers::<BASE_ADDRESS>() })
+    }
+
+    fn set_deadline(&self, deadline: u64) -> Result<(), DeadlinePassed> {
+        self.deadline.store(deadline, Ordering::Relaxed);
+        self.armed.store(true, Ordering::Release);
+        self.compare(deadline);
+
+        // The counter may have passed the compare value before it was written, in which case the
+        // channel is disarmed so that a late match isn't reported
+        if counter(unsafe { registers::<BASE_ADDRESS>() }) >= deadline {
+            self.armed.store(false, Ordering::Release);
+            return Err(DeadlinePassed);
+        }
+
+        Ok(())
+    }
+
+    fn cancel(&self) {
+        self.armed.store(false, Ordering::Release);
+
+        let registers = unsafe { registers::<BASE_ADDRESS>() };
+        registers.CS.set(1 << CHANNEL);
+    }
+}
+
+/// Fetch the register block of the system timer at `BASE_ADDRESS`.
+///
+/// # Safety
+///
+/// `BASE_ADDRESS` must be the address of the memory-mapped registers of the system timer.
+unsafe fn registers<const BASE_ADDRESS: usize>() -> &'static RegisterBlock {
+    &*(BASE_ADDRESS as *const RegisterBlock)
+}
+
+/// Read the 64 bit counter, which is split across two registers.
+fn counter(registers: &RegisterBlock) -> u64 {
+    loop {
+        let high = registers.CHI.get();
+        let low = registers.CLO.get();
+
+        // The lower half may have overflowed between the reads
+        if registers.CHI.get() == high {
+            return (high as u64) << 32 | low as u64;
+        }
+    }
+}
+
+register_structs! {
+    #[allow(non_snake_case)]
+    RegisterBlock {
+        /// Control/Status, where each of the lower four bits indicates a match on that channel,
+        /// and is cleared by writing a `1`.
+        (0x00 => CS: ReadWrite<u32>),
+        /// Counter lower 32 bits.
+        (0x04 => CLO: ReadOnly<u32>),
+        /// Counter higher 32 bits.
+        (0x08 => CHI: ReadOnly<u32>),
+        /// Compare value of each channel.
+        (0x0C => C: [ReadWrite<u32>; 4]),
+        (0x1C => @END),
+    }
+}
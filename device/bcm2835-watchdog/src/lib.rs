@@ -0,0 +1,207 @@
+//! Driver for the watchdog of the BCM2835 power management block, which is also the only way to
+//! reset the board.
+//!
+//! Once started, the watchdog resets the board when its timer runs out, unless it is kicked first.
+//! Rebooting (or halting) is done by letting the watchdog expire almost immediately. The firmware
+//! reads the boot partition from the reset status register when it restarts, where partition 63
+//! tells it to halt rather than boot again.
+//!
+//! Every write to these registers must include a password in the upper byte, otherwise it is
+//! ignored. The registers aren't covered by the BCM2835 ARM Peripherals document, so these details
+//! match the driver in Linux (`drivers/watchdog/bcm2835_wdt.c`).
+
+#![no_std]
+
+use core::{
+    sync::atomic::{AtomicU32, Ordering},
+    time::Duration,
+};
+
+use tock_registers::{interfaces::*, register_bitfields, register_structs, registers::*};
+
+/// Frequency at which the watchdog timer counts down, in Hz.
+const TICKS_PER_SECOND: u64 = 1 << 16;
+
+/// Largest number of ticks that the watchdog timer can hold.
+const MAX_TICKS: u32 = (1 << 20) - 1;
+
+/// Number of ticks used to reset the board as soon as possible.
+const RESET_TICKS: u32 = 10;
+
+/// Partition which tells the firmware to halt instead of booting.
+const HALT_PARTITION: u32 = 63;
+
+/// Longest timeout that the watchdog supports, just under 16 seconds.
+pub const MAX_TIMEOUT: Duration =
+    Duration::from_micros(MAX_TICKS as u64 * 1_000_000 / TICKS_PER_SECOND);
+
+/// The watchdog of the power management block.
+pub struct Watchdog<const BASE_ADDRESS: usize> {
+    /// Ticks to reload the timer with each time that it is kicked.
+    timeout: AtomicU32,
+}
+
+impl<const BASE_ADDRESS: usize> Watchdog<BASE_ADDRESS> {
+    /// Create a new instance, without starting the watchdog.
+    pub const fn new() -> Self {
+        Self {
+            timeout: AtomicU32::new(0),
+        }
+    }
+
+    /// Fetch the register block of this instance.
+    ///
+    /// # Safety
+    ///
+    /// `BASE_ADDRESS` must be a valid memory address, and point to the start of the memory-mapped
+    /// registers of the power management block.
+    unsafe fn registers(&self) -> &'static RegisterBlock {
+        &*(BASE_ADDRESS as *const RegisterBlock)
+    }
+
+    /// Start the watchdog, which will reset the board unless kicked within `timeout`. The timeout
+    /// is limited to [`MAX_TIMEOUT`]. If it is already running, the timeout is replaced.
+    pub fn start(&self, timeout: Duration) {
+        let ticks = (timeout.as_nanos() * TICKS_PER_SECOND as u128 / 1_000_000_000)
+            .clamp(1, MAX_TICKS as u128) as u32;
+
+        self.timeout.store(ticks, Ordering::Relaxed);
+        self.arm(ticks);
+    }
+
+    /// Restart the countdown of a running watchdog. Has no effect if it hasn't been started.
+    pub fn kick(&self) {
+        let ticks = self.timeout.load(Ordering::Relaxed);
+
+        if ticks != 0 {
+            let registers = unsafe { self.registers() };
+
+            registers
+                .WDOG
+                .write(WDOG::PASSWORD::Password + WDOG::TIME.val(ticks));
+        }
+    }
+
+    /// Stop the watchdog, so it will no longer reset the board.
+    pub fn stop(&self) {
+        self.timeout.store(0, Ordering::Relaxed);
+
+        let registers = unsafe { self.registers() };
+
+        registers
+            .RSTC
+            .write(RSTC::PASSWORD::Password + RSTC::RESET::Stop);
+    }
+
+    /// Time left before the watchdog resets the board, if it has been started.
+    pub fn remaining(&self) -> Option<Duration> {
+        if self.timeout.load(Ordering::Relaxed) == 0 {
+            return None;
+        }
+
+        let registers = unsafe { self.registers() };
+        let ticks = registers.WDOG.read(WDOG::TIME) as u64;
+
+        Some(Duration::from_micros(ticks * 1_000_000 / TICKS_PER_SECOND))
+    }
+
+    /// Reset the board.
+    pub fn reboot(&self) -> ! {
+        self.reset(0)
+    }
+
+    /// Reset the board, telling the firmware to halt instead of booting again. The board stays
+    /// powered, but nothing runs until it is power cycled.
+    pub fn halt(&self) -> ! {
+        self.reset(HALT_PARTITION)
+    }
+
+    /// Reset the board almost immediately, booting from `partition` afterwards.
+    fn reset(&self, partition: u32) -> ! {
+        let registers = unsafe { self.registers() };
+
+        // The partition is spread across the even bits of the register
+        let partition_bits = (0..6).fold(0, |bits, bit| bits | (partition >> bit & 1) << (bit * 2));
+        let status = registers.RSTS.get() & !PARTITION_MASK;
+
+        registers
+            .RSTS
+            .set(status | partition_bits | RSTS::PASSWORD::Password.value);
+
+        self.arm(RESET_TICKS);
+
+        loop {
+            core::hint::spin_loop();
+        }
+    }
+
+    /// Load the timer with `ticks`, and enable a full reset once it expires.
+    fn arm(&self, ticks: u32) {
+        let registers = unsafe { self.registers() };
+
+        registers
+            .WDOG
+            .write(WDOG::PASSWORD::Password + WDOG::TIME.val(ticks));
+        registers
+            .RSTC
+            .modify(RSTC::PASSWORD::Password + RSTC::WRCFG::FullReset);
+    }
+}
+
+impl<const BASE_ADDRESS: usize> Default for Watchdog<BASE_ADDRESS> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Bits of the reset status register that hold the boot partition.
+const PARTITION_MASK: u32 = 0x555;
+
+register_bitfields! {
+    u32,
+
+    /// Reset Control
+    RSTC [
+        /// Must be written with every value
+        PASSWORD OFFSET(24) NUMBITS(8) [
+            Password = 0x5A,
+        ],
+        /// Action to take when the watchdog expires
+        WRCFG OFFSET(4) NUMBITS(2) [
+            FullReset = 0b10,
+        ],
+        /// Writing this value stops the watchdog
+        RESET OFFSET(0) NUMBITS(12) [
+            Stop = 0x102,
+        ],
+    ],
+
+    /// Reset Status
+    RSTS [
+        /// Must be written with every value
+        PASSWORD OFFSET(24) NUMBITS(8) [
+            Password = 0x5A,
+        ],
+    ],
+
+    /// Watchdog
+    WDOG [
+        /// Must be written with every value
+        PASSWORD OFFSET(24) NUMBITS(8) [
+            Password = 0x5A,
+        ],
+        /// Ticks remaining until the watchdog expires
+        TIME OFFSET(0) NUMBITS(20) [],
+    ],
+}
+
+register_structs! {
+    #[allow(non_snake_case)]
+    RegisterBlock {
+        (0x00 => _reserved),
+        (0x1C => RSTC: ReadWrite<u32, RSTC::Register>),
+        (0x20 => RSTS: ReadWrite<u32, RSTS::Register>),
+        (0x24 => WDOG: ReadWrite<u32, WDOG::Register>),
+        (0x28 => @END),
+    }
+}
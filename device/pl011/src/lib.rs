@@ -2,6 +2,11 @@
 
 use core::{fmt, marker::PhantomData};
 
+pub use lib_kernel::console::ReceiveErrors;
+use lib_kernel::{
+    console::Uart,
+    ring_buffer::{Consumer, Producer},
+};
 use tock_registers::{interfaces::*, register_bitfields, register_structs, registers::*};
 
 pub enum Uninitialised {}
@@ -9,6 +14,9 @@ pub enum Initialised {}
 
 pub struct Pl011<const BASE_ADDRESS: usize, I = Uninitialised> {
     _init_state: PhantomData<I>,
+
+    /// Running count of errors encountered whilst receiving.
+    receive_errors: ReceiveErrors,
 }
 
 impl<const BASE_ADDRESS: usize, I> Pl011<BASE_ADDRESS, I> {
@@ -16,6 +24,7 @@ impl<const BASE_ADDRESS: usize, I> Pl011<BASE_ADDRESS, I> {
     pub fn new() -> Pl011<BASE_ADDRESS, Uninitialised> {
         Pl011 {
             _init_state: PhantomData,
+            receive_errors: ReceiveErrors::default(),
         }
     }
 
@@ -49,7 +58,9 @@ impl<const BASE_ADDRESS: usize> Pl011<BASE_ADDRESS, Uninitialised> {
         registers
             .LCR_H
             .write(LCR_H::WLEN::EightBit + LCR_H::FEN::FifosEnabled);
-        registers.IFLS.write(IFLS::RXIFLSEL::OneEighth);
+        registers
+            .IFLS
+            .write(IFLS::RXIFLSEL::OneEighth + IFLS::TXIFLSEL::OneEighth);
 
         // Enable interrupts for receive and receive timeout
         registers
@@ -63,33 +74,132 @@ impl<const BASE_ADDRESS: usize> Pl011<BASE_ADDRESS, Uninitialised> {
 
         Pl011 {
             _init_state: PhantomData,
+            receive_errors: self.receive_errors,
         }
     }
 }
 
 impl<const BASE_ADDRESS: usize> Pl011<BASE_ADDRESS, Initialised> {
-    /// Block until all bytes have been transmitted.
-    fn flush(&self) {
+    /// Create another handle to the PL011, once it has already been initialised. This allows it
+    /// to be written to when the original handle is unavailable, such as whilst panicking.
+    ///
+    /// # Safety
+    ///
+    /// The peripheral must have been initialised with [`Pl011::initialise`]. Anything written
+    /// through each handle may be interleaved if they are used at the same time.
+    pub unsafe fn steal() -> Self {
+        Pl011 {
+            _init_state: PhantomData,
+            receive_errors: ReceiveErrors::default(),
+        }
+    }
+
+    /// Write a single byte, blocking until there is space for it in the transmit FIFO.
+    fn write_byte(&mut self, byte: u8) {
         let registers = unsafe { self.registers() };
 
-        while registers.FR.matches_all(FR::TXFF::SET) {
-            // TODO: nop
+        while registers.FR.is_set(FR::TXFF) {
+            core::hint::spin_loop();
         }
+
+        registers.DR.set(byte as u32);
     }
+}
 
-    fn write_char(&mut self, c: char) {
+impl<const BASE_ADDRESS: usize> Uart for Pl011<BASE_ADDRESS, Initialised> {
+    fn handle_interrupt<const RX: usize, const TX: usize>(
+        &mut self,
+        rx: &mut Producer<'_, RX>,
+        tx: &mut Consumer<'_, TX>,
+    ) {
         let registers = unsafe { self.registers() };
 
-        // TODO: Wait for TX FIFO to havea slot
+        if registers.MIS.is_set(MIS::TXMIS) {
+            self.transmit(tx);
+        }
+
+        while !registers.FR.is_set(FR::RXFE) {
+            let data = registers.DR.extract();
+
+            if data.is_set(DR::OE) {
+                // The character is still valid, but the ones following it were lost
+                self.receive_errors.overrun += 1;
+            }
+
+            if data.is_set(DR::BE) {
+                self.receive_errors.break_condition += 1;
+                continue;
+            }
+
+            if data.is_set(DR::FE) {
+                self.receive_errors.framing += 1;
+                continue;
+            }
+
+            if data.is_set(DR::PE) {
+                self.receive_errors.parity += 1;
+                continue;
+            }
+
+            if rx.push(data.read(DR::DATA) as u8).is_err() {
+                self.receive_errors.dropped += 1;
+            }
+        }
+
+        // The FIFO is empty, so the receive interrupts can be cleared
+        registers.ICR.write(
+            ICR::OEIC::SET
+                + ICR::BEIC::SET
+                + ICR::PEIC::SET
+                + ICR::FEIC::SET
+                + ICR::RTIC::SET
+                + ICR::RXIC::SET,
+        );
+    }
+
+    fn receive_errors(&self) -> ReceiveErrors {
+        self.receive_errors
+    }
+
+    fn transmit<const N: usize>(&mut self, tx: &mut Consumer<'_, N>) {
+        let registers = unsafe { self.registers() };
+
+        while !registers.FR.is_set(FR::TXFF) {
+            let Some(byte) = tx.pop() else {
+                // Nothing left to send, so there's no need to be notified when the FIFO drains
+                registers.IMSC.modify(IMSC::TXIM::Disabled);
+                registers.ICR.write(ICR::TXIC::SET);
+
+                return;
+            };
+
+            registers.DR.set(byte as u32);
+        }
+
+        // The FIFO is full, so the interrupt will fire once it has drained below the trigger level
+        registers.IMSC.modify(IMSC::TXIM::Enabled);
+    }
 
-        registers.DR.set(c as u32);
+    fn transmit_blocking<const N: usize>(&mut self, tx: &mut Consumer<'_, N>) {
+        while let Some(byte) = tx.pop() {
+            self.write_byte(byte);
+        }
+    }
+
+    fn flush(&self) {
+        let registers = unsafe { self.registers() };
+
+        while registers.FR.is_set(FR::BUSY) {
+            core::hint::spin_loop();
+        }
     }
 }
 
+/// Synchronously writes directly to the transmit FIFO, blocking whenever it is full.
 impl<const BASE_ADDRESS: usize> fmt::Write for Pl011<BASE_ADDRESS, Initialised> {
     fn write_str(&mut self, s: &str) -> fmt::Result {
-        for c in s.chars() {
-            self.write_char(c);
+        for byte in s.bytes() {
+            self.write_byte(byte);
         }
 
         Ok(())
@@ -99,6 +209,20 @@ impl<const BASE_ADDRESS: usize> fmt::Write for Pl011<BASE_ADDRESS, Initialised>
 register_bitfields! {
     u32,
 
+    /// Data Register
+    DR [
+        /// Overrun error
+        OE OFFSET(11) NUMBITS(1) [],
+        /// Break error
+        BE OFFSET(10) NUMBITS(1) [],
+        /// Parity error
+        PE OFFSET(9) NUMBITS(1) [],
+        /// Framing error
+        FE OFFSET(8) NUMBITS(1) [],
+        /// Data character
+        DATA OFFSET(0) NUMBITS(8) [],
+    ],
+
     /// Flag Register
     FR [
         /// Transmit FIFO empty
@@ -164,7 +288,17 @@ register_bitfields! {
     IFLS [
         /// Receive interrupt FIFO level select. The trigger points for the receive interrupt are
         /// as follows.
-        RXIFLSEL OFFSET(3) NUMBITS(5) [
+        RXIFLSEL OFFSET(3) NUMBITS(3) [
+            OneEighth = 0b000,
+            OneQuarter = 0b001,
+            OneHalf = 0b010,
+            ThreeQuarters = 0b011,
+            SevenEighths = 0b100,
+        ],
+
+        /// Transmit interrupt FIFO level select. The trigger points for the transmit interrupt are
+        /// as follows.
+        TXIFLSEL OFFSET(0) NUMBITS(3) [
             OneEighth = 0b000,
             OneQuarter = 0b001,
             OneHalf = 0b010,
@@ -185,6 +319,15 @@ register_bitfields! {
             Enabled = 1,
         ],
 
+        /// Transmit interrupt mask. A read returns the current mask for the UARTTXINTR interrupt.
+        ///
+        /// - On a write of 1, the mask of the UARTTXINTR interrupt is set.
+        /// - A write of 0 clears the mask.
+        TXIM OFFSET(5) NUMBITS(1) [
+            Disabled = 0,
+            Enabled = 1,
+        ],
+
         /// Receive interrupt mask. A read returns the current mask for the UARTRXINTR interrupt.
         ///
         /// - On a write of 1, the mask of the UARTRXINTR interrupt is set.
@@ -201,6 +344,10 @@ register_bitfields! {
         /// UARTRTINTR interrupt.
         RTMIS OFFSET(6) NUMBITS(1) [],
 
+        /// Transmit masked interrupt status. Returns the masked interrupt state of the UARTTXINTR
+        /// interrupt.
+        TXMIS OFFSET(5) NUMBITS(1) [],
+
         /// Receive masked interrupt status. Returns the masked interrupt state of the UARTRXINTR
         /// interrupt.
         RXMIS OFFSET(4) NUMBITS(1) [],
@@ -208,6 +355,21 @@ register_bitfields! {
 
     /// Interrupt Clear Register
     ICR [
+        /// Overrun error interrupt clear
+        OEIC OFFSET(10) NUMBITS(1) [],
+        /// Break error interrupt clear
+        BEIC OFFSET(9) NUMBITS(1) [],
+        /// Parity error interrupt clear
+        PEIC OFFSET(8) NUMBITS(1) [],
+        /// Framing error interrupt clear
+        FEIC OFFSET(7) NUMBITS(1) [],
+        /// Receive timeout interrupt clear
+        RTIC OFFSET(6) NUMBITS(1) [],
+        /// Transmit interrupt clear
+        TXIC OFFSET(5) NUMBITS(1) [],
+        /// Receive interrupt clear
+        RXIC OFFSET(4) NUMBITS(1) [],
+
         /// Meta field for all pending interrupts.
         ALL OFFSET(0) NUMBITS(11) [],
     ],
@@ -216,7 +378,7 @@ register_bitfields! {
 register_structs! {
     #[allow(non_snake_case)]
     pub RegisterBlock {
-        (0x00 => DR: ReadWrite<u32>),
+        (0x00 => DR: ReadWrite<u32, DR::Register>),
         (0x04 => _reserved1),
         (0x18 => FR: ReadOnly<u32, FR::Register>),
         (0x1C => _reserved2),
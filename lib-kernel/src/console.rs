@@ -0,0 +1,54 @@
+//! Interface shared by the UARTs that a board may use as its debug console.
+
+use core::fmt;
+
+use crate::ring_buffer::{Consumer, Producer};
+
+/// Count of each error condition encountered whilst receiving. UARTs which can't detect a
+/// condition leave its count at zero.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ReceiveErrors {
+    /// Data was received whilst the receive FIFO was full.
+    pub overrun: usize,
+    /// A received character did not have a valid stop bit.
+    pub framing: usize,
+    /// The parity of a received character did not match.
+    pub parity: usize,
+    /// A break condition was detected on the line.
+    pub break_condition: usize,
+    /// A received character was discarded, as there was no room left in the receive buffer.
+    pub dropped: usize,
+}
+
+/// An initialised UART, which transmits from and receives into ring buffers in the background
+/// using its interrupt.
+///
+/// Writing through [`fmt::Write`] bypasses the buffers, blocking whenever the transmit FIFO is
+/// full.
+pub trait Uart: fmt::Write {
+    /// Service a pending interrupt, draining the receive FIFO into `rx` and refilling the transmit
+    /// FIFO from `tx`.
+    ///
+    /// Characters received with errors are discarded, and every error is recorded in
+    /// [`Uart::receive_errors`].
+    fn handle_interrupt<const RX: usize, const TX: usize>(
+        &mut self,
+        rx: &mut Producer<'_, RX>,
+        tx: &mut Consumer<'_, TX>,
+    );
+
+    /// Move as many bytes from `tx` into the transmit FIFO as will fit, without blocking.
+    ///
+    /// Whilst bytes remain in `tx`, the transmit interrupt is enabled so that
+    /// [`Uart::handle_interrupt`] can continue to refill the FIFO in the background.
+    fn transmit<const N: usize>(&mut self, tx: &mut Consumer<'_, N>);
+
+    /// Transmit every byte in `tx`, blocking until they have all been accepted by the FIFO.
+    fn transmit_blocking<const N: usize>(&mut self, tx: &mut Consumer<'_, N>);
+
+    /// Block until all bytes have been transmitted.
+    fn flush(&self);
+
+    /// Count of all errors encountered whilst receiving.
+    fn receive_errors(&self) -> ReceiveErrors;
+}
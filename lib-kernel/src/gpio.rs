@@ -0,0 +1,47 @@
+//! Interrupts from general purpose I/O pins, such as from buttons or the interrupt lines of
+//! external devices.
+//!
+//! Pins are identified by their number on the board's GPIO controller. Once an interrupt is
+//! enabled for a pin, the board delivers it to the kernel as [`crate::Interrupt::Gpio`].
+
+/// Condition on a pin which raises an interrupt.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Trigger {
+    /// The pin changing from low to high.
+    RisingEdge,
+    /// The pin changing from high to low.
+    FallingEdge,
+    /// The pin changing in either direction.
+    BothEdges,
+    /// The pin being high.
+    High,
+    /// The pin being low.
+    Low,
+}
+
+impl Trigger {
+    /// Whether the interrupt is raised for as long as the pin stays at a level, rather than once
+    /// when it changes.
+    pub const fn is_level(self) -> bool {
+        matches!(self, Trigger::High | Trigger::Low)
+    }
+}
+
+/// Internal resistor applied to a pin, so that it has a defined level when nothing drives it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pull {
+    None,
+    Down,
+    Up,
+}
+
+/// Reason that an interrupt could not be enabled for a pin.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GpioError {
+    /// The board has no pin with this number.
+    InvalidPin,
+    /// The pin is already in use, by the board itself or another interrupt.
+    InUse,
+    /// The board (or the kernel) can't provide the requested interrupt.
+    Unsupported,
+}
@@ -0,0 +1,150 @@
+//! Inter-processor interrupts, allowing a core to interrupt another with a message.
+
+use core::{
+    hint::spin_loop,
+    marker::PhantomData,
+    ptr,
+    sync::atomic::{AtomicBool, AtomicPtr, Ordering},
+};
+
+use crate::{Arch, Bsp};
+
+/// Message that can be sent to another core.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Ipi {
+    /// The core should re-evaluate which thread it is running.
+    Reschedule = 0,
+    /// The core should invalidate its TLB, as translation tables have changed.
+    TlbShootdown = 1,
+    /// The core has a pending request from [`CrossCall`].
+    CallFunction = 2,
+    /// Another core has panicked, so this core should stop.
+    Panic = 3,
+    /// The core has work queued by another core, which should run once the interrupt is handled.
+    Work = 4,
+}
+
+impl Ipi {
+    /// Every message, in order of their bit position.
+    pub const ALL: [Ipi; 5] = [
+        Ipi::Reschedule,
+        Ipi::TlbShootdown,
+        Ipi::CallFunction,
+        Ipi::Panic,
+        Ipi::Work,
+    ];
+
+    /// Bit representing this message, so multiple messages can be pending at once.
+    pub const fn bit(self) -> u32 {
+        1 << self as u32
+    }
+
+    /// Iterate over every message present in `bits`.
+    pub fn from_bits(bits: u32) -> impl Iterator<Item = Ipi> {
+        Self::ALL
+            .into_iter()
+            .filter(move |ipi| bits & ipi.bit() != 0)
+    }
+}
+
+/// Closure to run on another core, which lives on the stack of the requesting core.
+struct Request<'a> {
+    func: &'a (dyn Fn() + Sync),
+    /// Set by the target core once `func` has completed.
+    done: AtomicBool,
+}
+
+/// Runs closures on other cores, waiting for them to complete. Each of the `N` cores has a single
+/// slot for an incoming request, so concurrent requests to the same core are serialised.
+pub struct CrossCall<A, const N: usize> {
+    slots: [AtomicPtr<Request<'static>>; N],
+    _arch: PhantomData<A>,
+}
+
+impl<A: Arch, const N: usize> CrossCall<A, N> {
+    /// Create a new instance with no pending requests.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `N` is less than [`Arch::MAX_CORES`].
+    pub const fn new() -> Self {
+        assert!(N >= A::MAX_CORES, "a slot must be provided for every core");
+
+        Self {
+            slots: [const { AtomicPtr::new(ptr::null_mut()) }; N],
+            _arch: PhantomData,
+        }
+    }
+
+    /// Run `f` on `core`, waiting until it has completed. If `core` is the current core, `f` is
+    /// run immediately.
+    ///
+    /// Whilst waiting, requests made to the current core are still serviced, so two cores calling
+    /// each other will not deadlock.
+    pub fn call_on<B, F>(&self, bsp: &B, core: usize, f: F)
+    where
+        B: Bsp<Arch = A>,
+        F: Fn() + Sync,
+    {
+        if core == A::core_id() {
+            f();
+            return;
+        }
+
+        let request = Request {
+            func: &f,
+            done: AtomicBool::new(false),
+        };
+
+        // The lifetime is erased, however the request is guaranteed to outlive its use as this
+        // will not return until the target core marks it as done.
+        let request_ptr = &request as *const Request as *mut Request<'static>;
+
+        // Wait for any other request to the core to be claimed
+        while self.slots[core]
+            .compare_exchange(
+                ptr::null_mut(),
+                request_ptr,
+                Ordering::AcqRel,
+                Ordering::Relaxed,
+            )
+            .is_err()
+        {
+            self.handle();
+            spin_loop();
+        }
+
+        bsp.send_ipi(core, Ipi::CallFunction);
+
+        while !request.done.load(Ordering::Acquire) {
+            self.handle();
+            spin_loop();
+        }
+    }
+
+    /// Run any request pending for the current core. Must be called upon receiving
+    /// [`Ipi::CallFunction`].
+    pub fn handle(&self) {
+        let slot = &self.slots[A::core_id()];
+
+        // Claim the request before running it, so that a nested IPI can't run it again
+        let request = slot.swap(ptr::null_mut(), Ordering::AcqRel);
+        if request.is_null() {
+            return;
+        }
+
+        // Safety: The requesting core keeps the request alive until `done` is set.
+        let request = unsafe { &*request };
+
+        (request.func)();
+
+        request.done.store(true, Ordering::Release);
+    }
+}
+
+impl<A: Arch, const N: usize> Default for CrossCall<A, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,6 +1,22 @@
 #![no_std]
 
-use core::fmt::Write;
+pub mod console;
+pub mod gpio;
+pub mod ipi;
+pub mod percpu;
+pub mod ring_buffer;
+pub mod sync;
+pub mod time;
+
+use core::{
+    fmt::{self, Write},
+    ops::Range,
+    time::Duration,
+};
+
+use gpio::{GpioError, Pull, Trigger};
+use ipi::Ipi;
+use time::{ClockEvent, ClockSource};
 
 /// All the required functionality that a board must provide to the kernel.
 pub trait Bsp {
@@ -12,6 +28,39 @@ pub trait Bsp {
     /// This may be useful to setup core devices on the board.
     fn initialise(&self) {}
 
+    /// Hook for the board to perform any initialisation required by each core, such as routing
+    /// interrupts to it. Called on every core (including the boot core) as it enters the kernel,
+    /// after [`Bsp::initialise`].
+    fn initialise_core(&self) {}
+
+    /// Start all other cores on the board, which will each enter the kernel's secondary entry
+    /// point.
+    ///
+    /// Boards that only support a single core do not need to implement this.
+    fn start_secondary_cores(&self) {}
+
+    /// Send an inter-processor interrupt to `core`, which will be delivered to the kernel on that
+    /// core as [`Interrupt::Ipi`].
+    ///
+    /// Boards that only support a single core do not need to implement this.
+    fn send_ipi(&self, _core: usize, _ipi: Ipi) {}
+
+    /// Send a non-maskable interrupt to `core`, which is delivered even whilst it has IRQs masked.
+    /// The kernel must pass it to [`Bsp::handle_nmi`] on that core.
+    ///
+    /// Boards that can't send non-maskable interrupts do nothing.
+    fn send_nmi(&self, _core: usize) {}
+
+    /// Acknowledge a non-maskable interrupt on the current core, calling `handler` if it was sent
+    /// with [`Bsp::send_nmi`].
+    ///
+    /// This may interrupt code holding any lock, so neither this nor `handler` may take locks.
+    fn handle_nmi<F>(&self, _handler: F)
+    where
+        F: FnOnce(),
+    {
+    }
+
     /// Run a closure with the debug console.
     ///
     /// If this board does not have a debug console, then the closure will not run, and [`None`]
@@ -19,6 +68,157 @@ pub trait Bsp {
     fn with_debug_console<F, T>(&self, f: F) -> Option<T>
     where
         F: FnOnce(&mut dyn Write) -> T;
+
+    /// Run a closure with the debug console, where all output is written synchronously.
+    ///
+    /// This should be used in cases where buffered output may never be transmitted, such as
+    /// within a panic. Any output that is already buffered will be written first. By default this
+    /// is the same as [`Bsp::with_debug_console`].
+    ///
+    /// The current core may already be using the console, such as when it panics part way through
+    /// writing, so boards which lock their console should override this to avoid waiting for it.
+    fn with_debug_console_sync<F, T>(&self, f: F) -> Option<T>
+    where
+        F: FnOnce(&mut dyn Write) -> T,
+    {
+        self.with_debug_console(f)
+    }
+
+    /// Read any bytes that have been received by the debug console into `buffer`, returning the
+    /// number of bytes read. This will never block, and returns `0` if nothing is available (or
+    /// the board has no debug console).
+    fn read_debug_console(&self, _buffer: &mut [u8]) -> usize {
+        0
+    }
+
+    /// Read bytes that have been received by the debug console into `buffer`, waiting until at
+    /// least one byte is available. Returns the number of bytes read.
+    ///
+    /// Interrupts must be enabled, otherwise this will never return.
+    fn read_debug_console_blocking(&self, buffer: &mut [u8]) -> usize {
+        loop {
+            // Interrupts are masked between checking and waiting, so that a byte arriving in
+            // between will still wake the core.
+            let read = without_interrupts::<Self::Arch, _, _>(|| {
+                let read = self.read_debug_console(buffer);
+
+                if read == 0 {
+                    Self::Arch::wait_for_interrupt();
+                }
+
+                read
+            });
+
+            if read > 0 {
+                return read;
+            }
+        }
+    }
+
+    /// Clock sources on the board, which the kernel chooses between (or compares) using their
+    /// ratings. At least one must be provided, and the order must never change.
+    fn clock_sources(&self) -> impl Iterator<Item = &dyn ClockSource>;
+
+    /// Clock event devices on the board, which the kernel chooses between using their ratings. At
+    /// least one must be per core, and the order must never change.
+    ///
+    /// Interrupts from each device are delivered as [`Interrupt::ClockEvent`].
+    fn clock_events(&self) -> impl Iterator<Item = &dyn ClockEvent>;
+
+    /// Reset the whole board.
+    ///
+    /// Boards which can't be reset halt the current core instead.
+    fn reboot(&self) -> ! {
+        halt::<Self::Arch>()
+    }
+
+    /// Power off the board, or otherwise stop it running anything until it is power cycled.
+    ///
+    /// Boards which can't be powered off halt the current core instead.
+    fn shutdown(&self) -> ! {
+        halt::<Self::Arch>()
+    }
+
+    /// Start the hardware watchdog, which resets the board unless [`Bsp::kick_watchdog`] is called
+    /// within `timeout`. Returns the timeout actually used, as it may be limited by the hardware,
+    /// or `None` if the board has no watchdog.
+    fn start_watchdog(&self, _timeout: Duration) -> Option<Duration> {
+        None
+    }
+
+    /// Restart the countdown of the hardware watchdog.
+    fn kick_watchdog(&self) {}
+
+    /// Stop the hardware watchdog, so it will no longer reset the board.
+    fn stop_watchdog(&self) {}
+
+    /// Raise [`Interrupt::Gpio`] whenever `trigger` occurs on `pin`, configuring the pin as an
+    /// input with the given pull resistor.
+    ///
+    /// Level triggered interrupts are only acknowledged once the kernel has handled them, so the
+    /// handler must stop the pin being driven to that level. Boards without GPIO interrupts return
+    /// [`GpioError::Unsupported`].
+    fn enable_gpio_interrupt(
+        &self,
+        _pin: usize,
+        _trigger: Trigger,
+        _pull: Pull,
+    ) -> Result<(), GpioError> {
+        Err(GpioError::Unsupported)
+    }
+
+    /// Stop raising interrupts for `pin`, and make it available to be used again.
+    fn disable_gpio_interrupt(&self, _pin: usize) {}
+
+    /// Current level of `pin`, or `None` if the board has no pin with this number.
+    fn gpio_is_high(&self, _pin: usize) -> Option<bool> {
+        None
+    }
+
+    /// Service all pending IRQs for the current core.
+    ///
+    /// Interrupts from devices owned by the board are handled internally, whilst any interrupt
+    /// that the kernel must act on is passed to `handler`.
+    fn handle_irq<F>(&self, _handler: F)
+    where
+        F: FnMut(Interrupt<'_>),
+    {
+    }
+}
+
+/// An interrupt which the kernel must respond to.
+#[derive(Clone, Copy)]
+pub enum Interrupt<'a> {
+    /// Another core sent a message with [`Bsp::send_ipi`].
+    Ipi(Ipi),
+    /// The deadline of a device in [`Bsp::clock_events`] has been reached.
+    ClockEvent(&'a dyn ClockEvent),
+    /// A GPIO pin enabled with [`Bsp::enable_gpio_interrupt`] has met its trigger, identified by
+    /// its number.
+    Gpio(usize),
+}
+
+impl fmt::Debug for Interrupt<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Interrupt::Ipi(ipi) => f.debug_tuple("Ipi").field(ipi).finish(),
+            Interrupt::ClockEvent(event) => {
+                f.debug_tuple("ClockEvent").field(&event.name()).finish()
+            }
+            Interrupt::Gpio(pin) => f.debug_tuple("Gpio").field(pin).finish(),
+        }
+    }
+}
+
+/// Action that the kernel takes once a panic has been reported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PanicPolicy {
+    /// Stop every core, leaving the board as it is so the panic can be investigated.
+    Halt,
+    /// Reset the board with [`Bsp::reboot`].
+    Reboot,
+    /// Power off the board with [`Bsp::shutdown`].
+    Shutdown,
 }
 
 /// Alias for a function with C FFI that takes no parameters and will never return to the caller.
@@ -30,4 +230,133 @@ pub trait Arch {
     ///
     /// For best effect, each function should be annotated with `#[no_mangle]`.
     const LINKER_FUNCTIONS: &[RawFunction];
+
+    /// Maximum number of cores that the architecture supports.
+    const MAX_CORES: usize;
+
+    /// Registers of code that was interrupted by an exception.
+    type ExceptionFrame: fmt::Debug + Clone + Send;
+
+    /// Saved state of a thread whilst it is not running on any core.
+    type ThreadContext: Send;
+
+    /// Context of a thread that has not been suspended yet, such as the code that each core boots
+    /// into. It will be filled in when first passed to [`Arch::switch_thread`] as `from`.
+    const EMPTY_THREAD_CONTEXT: Self::ThreadContext;
+
+    /// Identifier of the current core, in the range `0..MAX_CORES`. This is expected to be cheap,
+    /// as it's used to look up all per-core state.
+    fn core_id() -> usize;
+
+    /// Unmask IRQs on the current core.
+    ///
+    /// # Safety
+    ///
+    /// Interrupt handlers must be ready to be run as soon as this is called.
+    unsafe fn enable_interrupts();
+
+    /// Mask IRQs on the current core.
+    fn disable_interrupts();
+
+    /// Determine whether IRQs are currently unmasked on the current core.
+    fn interrupts_enabled() -> bool;
+
+    /// Determine whether the current core is running an interrupt handler.
+    fn in_interrupt() -> bool;
+
+    /// Number of nested interrupt handlers that the current core is running.
+    fn interrupt_depth() -> usize;
+
+    /// Replace the number of nested interrupt handlers that the current core is running, such as
+    /// to run work deferred by a handler as though it were ordinary code.
+    ///
+    /// # Safety
+    ///
+    /// The depth must be restored before the outermost handler returns, and IRQs must be masked
+    /// when it is.
+    unsafe fn set_interrupt_depth(depth: usize);
+
+    /// Halt the current core until an interrupt is pending.
+    fn wait_for_interrupt();
+
+    /// Halt the current core until an event is signalled with [`Arch::send_event`] (or an
+    /// interrupt is pending). May also return spuriously, so callers must re-check whatever they
+    /// are waiting for.
+    fn wait_for_event();
+
+    /// Wake all cores waiting in [`Arch::wait_for_event`]. Any memory writes made before this call
+    /// will be visible to the woken cores.
+    fn send_event();
+
+    /// Current value of the free-running system counter.
+    fn counter() -> u64;
+
+    /// Frequency of the system counter, in Hz.
+    fn counter_frequency() -> u64;
+
+    /// Create the context for a new thread, which will call `entry(arg)` using `stack` when it is
+    /// first switched to.
+    fn new_thread_context(
+        stack: Range<usize>,
+        entry: extern "C" fn(usize) -> !,
+        arg: usize,
+    ) -> Self::ThreadContext;
+
+    /// Suspend the current thread into `from`, and resume the thread in `to`. Returns once another
+    /// thread switches back to `from`, which may be on a different core.
+    ///
+    /// # Safety
+    ///
+    /// IRQs must be masked. `to` must have been suspended by this function or created by
+    /// [`Arch::new_thread_context`], and must not be running or resumed on any other core. Both
+    /// contexts, and the stacks of their threads, must remain valid until they are resumed.
+    unsafe fn switch_thread(from: *mut Self::ThreadContext, to: *const Self::ThreadContext);
+
+    /// Invalidate all cached address translations on the current core.
+    fn invalidate_tlb();
+
+    /// Write any cached data in `start..start + len` back to memory, so that it can be read by a
+    /// device which doesn't see the caches of the cores.
+    fn clean_dcache(start: usize, len: usize);
+
+    /// Discard any cached copies of `start..start + len`, so that data written to memory by a
+    /// device is seen by the current core. Cache lines only partly in the range are written back
+    /// first, so neighbouring data isn't lost.
+    fn invalidate_dcache(start: usize, len: usize);
+
+    /// Fill `addresses` with the call stack of the code interrupted at `frame`, starting with the
+    /// address at which it was interrupted, followed by each return address. Returns the number of
+    /// addresses written.
+    ///
+    /// Relies on the interrupted code maintaining frame pointers, and stops at the first frame
+    /// that doesn't look valid, or that isn't on the stack of the current thread.
+    fn backtrace(frame: &Self::ExceptionFrame, addresses: &mut [usize]) -> usize;
+}
+
+/// Stop the current core permanently.
+fn halt<A: Arch>() -> ! {
+    A::disable_interrupts();
+
+    loop {
+        A::wait_for_interrupt();
+    }
+}
+
+/// Run a closure with IRQs masked on the current core, restoring the previous state afterwards.
+pub fn without_interrupts<A, F, T>(f: F) -> T
+where
+    A: Arch,
+    F: FnOnce() -> T,
+{
+    let enabled = A::interrupts_enabled();
+    A::disable_interrupts();
+
+    let result = f();
+
+    if enabled {
+        // Safety: Interrupts were enabled before this call, so handlers must be ready.
+        unsafe { A::enable_interrupts() };
+    }
+
+    result
 }
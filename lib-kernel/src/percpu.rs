@@ -0,0 +1,60 @@
+//! Storage with a separate value for each core, so that core-local state can be kept without
+//! locks.
+
+use core::marker::PhantomData;
+
+use crate::{without_interrupts, Arch};
+
+/// A value of `T` for each of the `N` cores of architecture `A`.
+///
+/// The value for the current core is accessed through [`PerCpu::with`], which masks interrupts so
+/// that the value can't be re-entered by an interrupt handler, or moved to another core. This
+/// allows `T` to use unsynchronised interior mutability, such as [`core::cell::Cell`].
+pub struct PerCpu<A, T, const N: usize> {
+    values: [T; N],
+    _arch: PhantomData<A>,
+}
+
+// Safety: Without `T: Sync`, each value is only ever accessed from the core that it belongs to.
+unsafe impl<A, T: Send, const N: usize> Sync for PerCpu<A, T, N> {}
+
+impl<A: Arch, T, const N: usize> PerCpu<A, T, N> {
+    /// Create a new instance, where `values[i]` belongs to core `i`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are fewer values than [`Arch::MAX_CORES`].
+    pub const fn new(values: [T; N]) -> Self {
+        assert!(N >= A::MAX_CORES, "a value must be provided for every core");
+
+        Self {
+            values,
+            _arch: PhantomData,
+        }
+    }
+
+    /// Run a closure with the value of the current core. Interrupts are masked whilst the closure
+    /// runs.
+    pub fn with<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&T) -> R,
+    {
+        without_interrupts::<A, _, _>(|| f(&self.values[A::core_id()]))
+    }
+
+    /// Fetch the value of a specific core, which may be accessed from any core.
+    pub fn get_for(&self, core: usize) -> &T
+    where
+        T: Sync,
+    {
+        &self.values[core]
+    }
+
+    /// Iterate over the values of every core.
+    pub fn iter(&self) -> impl Iterator<Item = &T>
+    where
+        T: Sync,
+    {
+        self.values.iter()
+    }
+}
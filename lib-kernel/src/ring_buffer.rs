@@ -0,0 +1,165 @@
+//! Lock-free, fixed-capacity byte queue for passing data between interrupt handlers and the rest
+//! of the kernel.
+//!
+//! Only a single [`Producer`] and a single [`Consumer`] can exist at any time, which is enforced at
+//! runtime when they are claimed. This allows each side to be used from a different context (such
+//! as an IRQ handler and a thread) without any locks.
+
+use core::{
+    cell::UnsafeCell,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+/// Byte queue with space for `N` bytes.
+pub struct RingBuffer<const N: usize> {
+    /// Backing storage for the queue.
+    buffer: UnsafeCell<[u8; N]>,
+
+    /// Total number of bytes that have been read. Only modified by the [`Consumer`].
+    head: AtomicUsize,
+    /// Total number of bytes that have been written. Only modified by the [`Producer`].
+    tail: AtomicUsize,
+
+    /// Whether a [`Producer`] currently exists.
+    producer_claimed: AtomicBool,
+    /// Whether a [`Consumer`] currently exists.
+    consumer_claimed: AtomicBool,
+}
+
+// Safety: Access to the buffer is only possible through the `Producer` and `Consumer`, which are
+// unique and only access the slots that they own (as determined by `head` and `tail`).
+unsafe impl<const N: usize> Sync for RingBuffer<N> {}
+
+impl<const N: usize> RingBuffer<N> {
+    /// Create a new, empty queue.
+    pub const fn new() -> Self {
+        Self {
+            buffer: UnsafeCell::new([0; N]),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+            producer_claimed: AtomicBool::new(false),
+            consumer_claimed: AtomicBool::new(false),
+        }
+    }
+
+    /// Claim the producing side of the queue. Returns [`None`] if it is already claimed.
+    pub fn producer(&self) -> Option<Producer<'_, N>> {
+        (!self.producer_claimed.swap(true, Ordering::Acquire)).then_some(Producer { queue: self })
+    }
+
+    /// Claim the consuming side of the queue. Returns [`None`] if it is already claimed.
+    pub fn consumer(&self) -> Option<Consumer<'_, N>> {
+        (!self.consumer_claimed.swap(true, Ordering::Acquire)).then_some(Consumer { queue: self })
+    }
+
+    /// Number of bytes currently in the queue.
+    pub fn len(&self) -> usize {
+        let tail = self.tail.load(Ordering::Acquire);
+        let head = self.head.load(Ordering::Acquire);
+
+        tail.wrapping_sub(head)
+    }
+
+    /// Determine whether the queue is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Determine whether the queue is full.
+    pub fn is_full(&self) -> bool {
+        self.len() == N
+    }
+}
+
+impl<const N: usize> Default for RingBuffer<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Unique handle to write into a [`RingBuffer`].
+pub struct Producer<'a, const N: usize> {
+    queue: &'a RingBuffer<N>,
+}
+
+impl<const N: usize> Producer<'_, N> {
+    /// Add a byte to the end of the queue, returning it if the queue is full.
+    pub fn push(&mut self, byte: u8) -> Result<(), u8> {
+        let tail = self.queue.tail.load(Ordering::Relaxed);
+        let head = self.queue.head.load(Ordering::Acquire);
+
+        if tail.wrapping_sub(head) == N {
+            return Err(byte);
+        }
+
+        // Safety: The slot at `tail` is outside of the range that the consumer may read, and this
+        // is the only producer.
+        unsafe { (*self.queue.buffer.get())[tail % N] = byte };
+
+        // Publish the byte to the consumer
+        self.queue
+            .tail
+            .store(tail.wrapping_add(1), Ordering::Release);
+
+        Ok(())
+    }
+
+    /// Add as many bytes from `bytes` as will fit in the queue, returning the number added.
+    pub fn push_slice(&mut self, bytes: &[u8]) -> usize {
+        bytes
+            .iter()
+            .take_while(|byte| self.push(**byte).is_ok())
+            .count()
+    }
+}
+
+impl<const N: usize> Drop for Producer<'_, N> {
+    fn drop(&mut self) {
+        self.queue.producer_claimed.store(false, Ordering::Release);
+    }
+}
+
+/// Unique handle to read from a [`RingBuffer`].
+pub struct Consumer<'a, const N: usize> {
+    queue: &'a RingBuffer<N>,
+}
+
+impl<const N: usize> Consumer<'_, N> {
+    /// Remove a byte from the front of the queue, if one is available.
+    pub fn pop(&mut self) -> Option<u8> {
+        let head = self.queue.head.load(Ordering::Relaxed);
+        let tail = self.queue.tail.load(Ordering::Acquire);
+
+        if head == tail {
+            return None;
+        }
+
+        // Safety: The slot at `head` has been published by the producer, and will not be written
+        // again until `head` is advanced past it.
+        let byte = unsafe { (*self.queue.buffer.get())[head % N] };
+
+        // Release the slot back to the producer
+        self.queue
+            .head
+            .store(head.wrapping_add(1), Ordering::Release);
+
+        Some(byte)
+    }
+
+    /// Remove as many bytes as are available into `buffer`, returning the number removed.
+    pub fn pop_slice(&mut self, buffer: &mut [u8]) -> usize {
+        buffer
+            .iter_mut()
+            .map_while(|slot| {
+                *slot = self.pop()?;
+                Some(())
+            })
+            .count()
+    }
+}
+
+impl<const N: usize> Drop for Consumer<'_, N> {
+    fn drop(&mut self) {
+        self.queue.consumer_claimed.store(false, Ordering::Release);
+    }
+}
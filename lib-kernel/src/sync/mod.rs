@@ -0,0 +1,30 @@
+//! Synchronisation primitives for sharing state between cores and interrupt handlers.
+//!
+//! Waiting cores are halted with [`Arch::wait_for_event`] rather than busy-spinning, and are woken
+//! by [`Arch::send_event`] whenever a lock is released.
+//!
+//! Only [`IrqSpinMutex`] masks interrupts. All other locks must never be taken from interrupt
+//! context, as the interrupted code may already hold them, which is checked in debug builds.
+
+mod once;
+mod rwlock;
+mod seqlock;
+mod spin_mutex;
+mod ticket_lock;
+
+pub use once::{Once, OnceCell};
+pub use rwlock::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+pub use seqlock::SeqLock;
+pub use spin_mutex::{IrqSpinMutex, IrqSpinMutexGuard, SpinMutex, SpinMutexGuard};
+pub use ticket_lock::{TicketLock, TicketLockGuard};
+
+use crate::Arch;
+
+/// Panic in debug builds if called from interrupt context, where taking a lock that doesn't mask
+/// interrupts may deadlock.
+fn debug_assert_not_in_interrupt<A: Arch>() {
+    debug_assert!(
+        !A::in_interrupt(),
+        "non-IRQ-safe lock taken in interrupt context"
+    );
+}
@@ -0,0 +1,137 @@
+use core::{
+    cell::UnsafeCell,
+    marker::PhantomData,
+    mem::MaybeUninit,
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+use crate::Arch;
+
+/// The closure has not yet been run.
+const INCOMPLETE: u8 = 0;
+/// A core is currently running the closure.
+const RUNNING: u8 = 1;
+/// The closure has finished.
+const COMPLETE: u8 = 2;
+
+/// One-time initialisation, such as for a board or driver.
+///
+/// If the closure panics, any other callers will wait forever, so it is expected that a panic
+/// halts the kernel. It must not be called from an interrupt handler whilst it may be running on
+/// the same core.
+pub struct Once<A> {
+    _arch: PhantomData<A>,
+    state: AtomicU8,
+}
+
+impl<A: Arch> Once<A> {
+    /// Create a new instance, where the closure has not yet been run.
+    pub const fn new() -> Self {
+        Self {
+            _arch: PhantomData,
+            state: AtomicU8::new(INCOMPLETE),
+        }
+    }
+
+    /// Run `f` if no closure has been run before. If another core is currently running its
+    /// closure, this waits until it is complete.
+    pub fn call_once(&self, f: impl FnOnce()) {
+        if self.is_completed() {
+            return;
+        }
+
+        match self
+            .state
+            .compare_exchange(INCOMPLETE, RUNNING, Ordering::Acquire, Ordering::Acquire)
+        {
+            Ok(_) => {
+                f();
+
+                self.state.store(COMPLETE, Ordering::Release);
+                A::send_event();
+            }
+            Err(_) => {
+                while !self.is_completed() {
+                    A::wait_for_event();
+                }
+            }
+        }
+    }
+
+    /// Determine whether a closure has finished running.
+    pub fn is_completed(&self) -> bool {
+        self.state.load(Ordering::Acquire) == COMPLETE
+    }
+}
+
+impl<A: Arch> Default for Once<A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A value which is initialised once, and can then be shared.
+pub struct OnceCell<A, T> {
+    once: Once<A>,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+// Safety: The value is only written once, by the core which claimed the `Once`, and is only read
+// once that write is complete.
+unsafe impl<A, T: Send + Sync> Sync for OnceCell<A, T> {}
+unsafe impl<A, T: Send> Send for OnceCell<A, T> {}
+
+impl<A: Arch, T> OnceCell<A, T> {
+    /// Create a new, uninitialised cell.
+    pub const fn new() -> Self {
+        Self {
+            once: Once::new(),
+            value: UnsafeCell::new(MaybeUninit::uninit()),
+        }
+    }
+
+    /// The value of the cell, if it has been initialised.
+    pub fn get(&self) -> Option<&T> {
+        // Safety: The value is never modified once initialised.
+        self.once
+            .is_completed()
+            .then(|| unsafe { (*self.value.get()).assume_init_ref() })
+    }
+
+    /// The value of the cell, initialising it with `f` if it hasn't been already.
+    pub fn get_or_init(&self, f: impl FnOnce() -> T) -> &T {
+        self.once.call_once(|| {
+            // Safety: Only the core running this closure has access to the value.
+            unsafe { (*self.value.get()).write(f()) };
+        });
+
+        self.get().expect("cell to be initialised")
+    }
+
+    /// Initialise the cell with `value`, returning it if the cell was already initialised.
+    pub fn set(&self, value: T) -> Result<(), T> {
+        let mut value = Some(value);
+
+        self.get_or_init(|| value.take().expect("value to only be taken once"));
+
+        match value {
+            Some(value) => Err(value),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<A: Arch, T> Default for OnceCell<A, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A, T> Drop for OnceCell<A, T> {
+    fn drop(&mut self) {
+        if *self.once.state.get_mut() == COMPLETE {
+            // Safety: The value was initialised, and can no longer be accessed.
+            unsafe { self.value.get_mut().assume_init_drop() };
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use core::{
+    cell::UnsafeCell,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use super::debug_assert_not_in_interrupt;
+use crate::Arch;
+
+/// Set whilst a writer holds the lock.
+const WRITER: usize = 1;
+/// Set whilst a writer is waiting for the lock, which prevents new readers from taking it.
+const WRITER_WAITING: usize = 1 << 1;
+/// Amount added to the state for each reader holding the lock.
+const READER: usize = 1 << 2;
+
+/// Reader-writer lock, allowing either any number of readers or a single writer.
+///
+/// Waiting writers are preferred over new readers, so a steady stream of readers can't starve
+/// them. This lock does not mask interrupts, so it must never be taken from interrupt context.
+pub struct RwLock<A, T: ?Sized> {
+    _arch: PhantomData<A>,
+    /// Flags for writers, and the number of readers in the remaining bits.
+    state: AtomicUsize,
+    data: UnsafeCell<T>,
+}
+
+// Safety: Writers have exclusive access, and readers only have shared access, so `T` must be
+// `Sync` for it to be shared between cores.
+unsafe impl<A, T: ?Sized + Send + Sync> Sync for RwLock<A, T> {}
+unsafe impl<A, T: ?Sized + Send> Send for RwLock<A, T> {}
+
+impl<A: Arch, T> RwLock<A, T> {
+    /// Create a new, unlocked lock.
+    pub const fn new(data: T) -> Self {
+        Self {
+            _arch: PhantomData,
+            state: AtomicUsize::new(0),
+            data: UnsafeCell::new(data),
+        }
+    }
+}
+
+impl<A: Arch, T: ?Sized> RwLock<A, T> {
+    /// Take shared access, waiting until there are no writers holding or waiting for the lock.
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, panics if called from interrupt context.
+    pub fn read(&self) -> RwLockReadGuard<'_, A, T> {
+        debug_assert_not_in_interrupt::<A>();
+
+        loop {
+            let state = self.state.load(Ordering::Relaxed);
+
+            if state & (WRITER | WRITER_WAITING) != 0 {
+                A::wait_for_event();
+                continue;
+            }
+
+            // Failing only means that another reader raced to take the lock, so retry immediately
+            if self
+                .state
+                .compare_exchange_weak(state, state + READER, Ordering::Acquire, Ordering::Relaxed)
+                .is_ok()
+            {
+                return RwLockReadGuard { lock: self };
+            }
+        }
+    }
+
+    /// Take shared access if there are no writers holding or waiting for the lock.
+    pub fn try_read(&self) -> Option<RwLockReadGuard<'_, A, T>> {
+        let state = self.state.load(Ordering::Relaxed);
+
+        if state & (WRITER | WRITER_WAITING) != 0 {
+            return None;
+        }
+
+        self.state
+            .compare_exchange(state, state + READER, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+            .then_some(RwLockReadGuard { lock: self })
+    }
+
+    /// Take exclusive access, waiting until all other readers and writers have released the lock.
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, panics if called from interrupt context.
+    pub fn write(&self) -> RwLockWriteGuard<'_, A, T> {
+        debug_assert_not_in_interrupt::<A>();
+
+        loop {
+            if let Some(guard) = self.try_write() {
+                return guard;
+            }
+
+            // Stop any new readers from taking the lock, so it will eventually become free
+            let state = self.state.load(Ordering::Relaxed);
+            if state & WRITER_WAITING == 0 {
+                self.state.fetch_or(WRITER_WAITING, Ordering::Relaxed);
+            }
+
+            A::wait_for_event();
+        }
+    }
+
+    /// Take exclusive access if no other readers or writers hold the lock.
+    pub fn try_write(&self) -> Option<RwLockWriteGuard<'_, A, T>> {
+        let state = self.state.load(Ordering::Relaxed);
+
+        if state & !WRITER_WAITING != 0 {
+            return None;
+        }
+
+        // Any other waiting writers will set the waiting flag again
+        self.state
+            .compare_exchange(state, WRITER, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+            .then_some(RwLockWriteGuard { lock: self })
+    }
+}
+
+/// Shared access to the data of a [`RwLock`], which is released when dropped.
+pub struct RwLockReadGuard<'a, A: Arch, T: ?Sized> {
+    lock: &'a RwLock<A, T>,
+}
+
+// Safety: The guard only hands out shared references to the data when it is shared.
+unsafe impl<A: Arch, T: ?Sized + Sync> Sync for RwLockReadGuard<'_, A, T> {}
+
+impl<A: Arch, T: ?Sized> Deref for RwLockReadGuard<'_, A, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // Safety: No writers can hold the lock for the lifetime of the guard.
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<A: Arch, T: ?Sized> Drop for RwLockReadGuard<'_, A, T> {
+    fn drop(&mut self) {
+        self.lock.state.fetch_sub(READER, Ordering::Release);
+        A::send_event();
+    }
+}
+
+/// Exclusive access to the data of a [`RwLock`], which is released when dropped.
+pub struct RwLockWriteGuard<'a, A: Arch, T: ?Sized> {
+    lock: &'a RwLock<A, T>,
+}
+
+// Safety: The guard only hands out shared references to the data when it is shared.
+unsafe impl<A: Arch, T: ?Sized + Sync> Sync for RwLockWriteGuard<'_, A, T> {}
+
+impl<A: Arch, T: ?Sized> Deref for RwLockWriteGuard<'_, A, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // Safety: The lock is held exclusively for the lifetime of the guard.
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<A: Arch, T: ?Sized> DerefMut for RwLockWriteGuard<'_, A, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // Safety: The lock is held exclusively for the lifetime of the guard.
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<A: Arch, T: ?Sized> Drop for RwLockWriteGuard<'_, A, T> {
+    fn drop(&mut self) {
+        // Leave the waiting flag, as another writer may have set it
+        self.lock.state.fetch_and(!WRITER, Ordering::Release);
+        A::send_event();
+    }
+}
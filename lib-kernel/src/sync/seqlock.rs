@@ -0,0 +1,90 @@
+use core::{
+    cell::UnsafeCell,
+    hint::spin_loop,
+    ptr,
+    sync::atomic::{fence, AtomicUsize, Ordering},
+};
+
+use super::spin_mutex::RawSpinLock;
+use crate::{without_interrupts, Arch};
+
+/// Sequence lock, for small, frequently read data such as timekeeping state.
+///
+/// Readers never block writers, and instead retry if a write happened whilst they were reading.
+/// Writers are serialised with each other, and mask interrupts whilst writing, so a reader in an
+/// interrupt handler can't spin forever on a write that it interrupted. Readers take no lock, so
+/// may be used from any context.
+pub struct SeqLock<A, T> {
+    /// Incremented before and after each write, so it is odd whilst a write is in progress.
+    sequence: AtomicUsize,
+    writer: RawSpinLock<A>,
+    data: UnsafeCell<T>,
+}
+
+// Safety: Readers only ever take copies of the data, which are discarded if they may be torn, and
+// writers are serialised by the lock.
+unsafe impl<A, T: Copy + Send> Sync for SeqLock<A, T> {}
+unsafe impl<A, T: Copy + Send> Send for SeqLock<A, T> {}
+
+impl<A: Arch, T: Copy> SeqLock<A, T> {
+    /// Create a new lock holding `data`.
+    pub const fn new(data: T) -> Self {
+        Self {
+            sequence: AtomicUsize::new(0),
+            writer: RawSpinLock::new(),
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    /// Take a consistent copy of the data, retrying whilst it is being written.
+    pub fn read(&self) -> T {
+        loop {
+            let start = self.sequence.load(Ordering::Acquire);
+
+            if start % 2 == 1 {
+                spin_loop();
+                continue;
+            }
+
+            // Safety: The copy may be torn by a concurrent write, but will be discarded if so.
+            // A volatile read stops the compiler assuming the data can't change underneath it.
+            let data = unsafe { ptr::read_volatile(self.data.get()) };
+
+            // Ensure the copy is complete before checking whether it may be torn
+            fence(Ordering::Acquire);
+
+            if self.sequence.load(Ordering::Relaxed) == start {
+                return data;
+            }
+        }
+    }
+
+    /// Modify the data in place. Concurrent readers will retry until the write is complete.
+    pub fn write<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        without_interrupts::<A, _, _>(|| {
+            self.writer.lock();
+
+            let sequence = self.sequence.load(Ordering::Relaxed);
+            self.sequence
+                .store(sequence.wrapping_add(1), Ordering::Relaxed);
+
+            // Ensure readers see the write as in progress before any of the data changes
+            fence(Ordering::Release);
+
+            // Safety: Writers are serialised by the lock, and readers only take copies.
+            let result = f(unsafe { &mut *self.data.get() });
+
+            self.sequence
+                .store(sequence.wrapping_add(2), Ordering::Release);
+
+            self.writer.unlock();
+
+            result
+        })
+    }
+
+    /// Replace the data.
+    pub fn set(&self, data: T) {
+        self.write(|value| *value = data);
+    }
+}
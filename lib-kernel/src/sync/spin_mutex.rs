@@ -0,0 +1,222 @@
+use core::{
+    cell::UnsafeCell,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use super::debug_assert_not_in_interrupt;
+use crate::Arch;
+
+/// Test-and-set lock, shared by the mutex implementations.
+pub(super) struct RawSpinLock<A> {
+    _arch: PhantomData<A>,
+    locked: AtomicBool,
+}
+
+impl<A: Arch> RawSpinLock<A> {
+    pub(super) const fn new() -> Self {
+        Self {
+            _arch: PhantomData,
+            locked: AtomicBool::new(false),
+        }
+    }
+
+    pub(super) fn try_lock(&self) -> bool {
+        self.locked
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+    }
+
+    pub(super) fn lock(&self) {
+        while !self.try_lock() {
+            // Wait for the lock to appear free before attempting to take it again
+            while self.locked.load(Ordering::Relaxed) {
+                A::wait_for_event();
+            }
+        }
+    }
+
+    pub(super) fn unlock(&self) {
+        self.locked.store(false, Ordering::Release);
+        A::send_event();
+    }
+}
+
+/// Mutual exclusion lock which spins whilst waiting.
+///
+/// This lock does not mask interrupts, so it must never be taken from interrupt context, as the
+/// interrupted code may already hold it. In debug builds, this is checked when locking. Use
+/// [`IrqSpinMutex`] for anything shared with interrupt handlers.
+pub struct SpinMutex<A, T: ?Sized> {
+    lock: RawSpinLock<A>,
+    data: UnsafeCell<T>,
+}
+
+// Safety: Access to the data is serialised by the lock.
+unsafe impl<A, T: ?Sized + Send> Sync for SpinMutex<A, T> {}
+unsafe impl<A, T: ?Sized + Send> Send for SpinMutex<A, T> {}
+
+impl<A: Arch, T> SpinMutex<A, T> {
+    /// Create a new, unlocked mutex.
+    pub const fn new(data: T) -> Self {
+        Self {
+            lock: RawSpinLock::new(),
+            data: UnsafeCell::new(data),
+        }
+    }
+}
+
+impl<A: Arch, T: ?Sized> SpinMutex<A, T> {
+    /// Take the lock, spinning until it is available.
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, panics if called from interrupt context.
+    pub fn lock(&self) -> SpinMutexGuard<'_, A, T> {
+        debug_assert_not_in_interrupt::<A>();
+
+        self.lock.lock();
+
+        SpinMutexGuard { mutex: self }
+    }
+
+    /// Take the lock if it is available, without spinning.
+    pub fn try_lock(&self) -> Option<SpinMutexGuard<'_, A, T>> {
+        self.lock
+            .try_lock()
+            .then_some(SpinMutexGuard { mutex: self })
+    }
+}
+
+/// Exclusive access to the data of a [`SpinMutex`], which is unlocked when dropped.
+pub struct SpinMutexGuard<'a, A: Arch, T: ?Sized> {
+    mutex: &'a SpinMutex<A, T>,
+}
+
+// Safety: The guard only hands out shared references to the data when it is shared.
+unsafe impl<A: Arch, T: ?Sized + Sync> Sync for SpinMutexGuard<'_, A, T> {}
+
+impl<A: Arch, T: ?Sized> Deref for SpinMutexGuard<'_, A, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // Safety: The lock is held for the lifetime of the guard.
+        unsafe { &*self.mutex.data.get() }
+    }
+}
+
+impl<A: Arch, T: ?Sized> DerefMut for SpinMutexGuard<'_, A, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // Safety: The lock is held for the lifetime of the guard.
+        unsafe { &mut *self.mutex.data.get() }
+    }
+}
+
+impl<A: Arch, T: ?Sized> Drop for SpinMutexGuard<'_, A, T> {
+    fn drop(&mut self) {
+        self.mutex.lock.unlock();
+    }
+}
+
+/// Mutual exclusion lock which masks IRQs on the current core whilst it is held, so it can safely
+/// be shared with interrupt handlers.
+pub struct IrqSpinMutex<A, T: ?Sized> {
+    lock: RawSpinLock<A>,
+    data: UnsafeCell<T>,
+}
+
+// Safety: Access to the data is serialised by the lock.
+unsafe impl<A, T: ?Sized + Send> Sync for IrqSpinMutex<A, T> {}
+unsafe impl<A, T: ?Sized + Send> Send for IrqSpinMutex<A, T> {}
+
+impl<A: Arch, T> IrqSpinMutex<A, T> {
+    /// Create a new, unlocked mutex.
+    pub const fn new(data: T) -> Self {
+        Self {
+            lock: RawSpinLock::new(),
+            data: UnsafeCell::new(data),
+        }
+    }
+}
+
+impl<A: Arch, T: ?Sized> IrqSpinMutex<A, T> {
+    /// Mask IRQs and take the lock, spinning until it is available.
+    pub fn lock(&self) -> IrqSpinMutexGuard<'_, A, T> {
+        let interrupts_enabled = A::interrupts_enabled();
+        A::disable_interrupts();
+
+        self.lock.lock();
+
+        IrqSpinMutexGuard {
+            mutex: self,
+            interrupts_enabled,
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Take the lock if it is available, without spinning. IRQs are only masked if the lock is
+    /// taken.
+    pub fn try_lock(&self) -> Option<IrqSpinMutexGuard<'_, A, T>> {
+        let interrupts_enabled = A::interrupts_enabled();
+        A::disable_interrupts();
+
+        if self.lock.try_lock() {
+            return Some(IrqSpinMutexGuard {
+                mutex: self,
+                interrupts_enabled,
+                _not_send: PhantomData,
+            });
+        }
+
+        if interrupts_enabled {
+            // Safety: Interrupts were enabled before this call, so handlers must be ready.
+            unsafe { A::enable_interrupts() };
+        }
+
+        None
+    }
+}
+
+/// Exclusive access to the data of an [`IrqSpinMutex`]. When dropped, the lock is released and
+/// IRQs are restored to their state before locking.
+///
+/// The guard can't be sent elsewhere, as the IRQ state it restores belongs to the core that took
+/// the lock.
+pub struct IrqSpinMutexGuard<'a, A: Arch, T: ?Sized> {
+    mutex: &'a IrqSpinMutex<A, T>,
+    /// Whether IRQs were enabled before the lock was taken.
+    interrupts_enabled: bool,
+    _not_send: PhantomData<*const ()>,
+}
+
+// Safety: The guard only hands out shared references to the data when it is shared.
+unsafe impl<A: Arch, T: ?Sized + Sync> Sync for IrqSpinMutexGuard<'_, A, T> {}
+
+impl<A: Arch, T: ?Sized> Deref for IrqSpinMutexGuard<'_, A, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // Safety: The lock is held for the lifetime of the guard.
+        unsafe { &*self.mutex.data.get() }
+    }
+}
+
+impl<A: Arch, T: ?Sized> DerefMut for IrqSpinMutexGuard<'_, A, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // Safety: The lock is held for the lifetime of the guard.
+        unsafe { &mut *self.mutex.data.get() }
+    }
+}
+
+impl<A: Arch, T: ?Sized> Drop for IrqSpinMutexGuard<'_, A, T> {
+    fn drop(&mut self) {
+        self.mutex.lock.unlock();
+
+        if self.interrupts_enabled {
+            // Safety: Interrupts were enabled before the lock was taken, so handlers must be
+            // ready.
+            unsafe { A::enable_interrupts() };
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use core::{
+    cell::UnsafeCell,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use super::debug_assert_not_in_interrupt;
+use crate::Arch;
+
+/// Fair mutual exclusion lock, which is granted to waiting cores in the order that they requested
+/// it.
+///
+/// Unlike [`super::SpinMutex`], a core can never be starved by others repeatedly taking the lock,
+/// at the cost of every waiting core being woken on each release. This lock does not mask
+/// interrupts, so it must never be taken from interrupt context.
+pub struct TicketLock<A, T: ?Sized> {
+    _arch: PhantomData<A>,
+    /// Ticket that will be given to the next core to request the lock.
+    next_ticket: AtomicUsize,
+    /// Ticket of the core which currently holds the lock.
+    now_serving: AtomicUsize,
+    data: UnsafeCell<T>,
+}
+
+// Safety: Access to the data is serialised by the lock.
+unsafe impl<A, T: ?Sized + Send> Sync for TicketLock<A, T> {}
+unsafe impl<A, T: ?Sized + Send> Send for TicketLock<A, T> {}
+
+impl<A: Arch, T> TicketLock<A, T> {
+    /// Create a new, unlocked lock.
+    pub const fn new(data: T) -> Self {
+        Self {
+            _arch: PhantomData,
+            next_ticket: AtomicUsize::new(0),
+            now_serving: AtomicUsize::new(0),
+            data: UnsafeCell::new(data),
+        }
+    }
+}
+
+impl<A: Arch, T: ?Sized> TicketLock<A, T> {
+    /// Take the lock, waiting until all cores that requested it earlier have released it.
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, panics if called from interrupt context.
+    pub fn lock(&self) -> TicketLockGuard<'_, A, T> {
+        debug_assert_not_in_interrupt::<A>();
+
+        let ticket = self.next_ticket.fetch_add(1, Ordering::Relaxed);
+
+        while self.now_serving.load(Ordering::Acquire) != ticket {
+            A::wait_for_event();
+        }
+
+        TicketLockGuard { lock: self }
+    }
+
+    /// Take the lock if no other core holds or is waiting for it.
+    pub fn try_lock(&self) -> Option<TicketLockGuard<'_, A, T>> {
+        let serving = self.now_serving.load(Ordering::Relaxed);
+
+        self.next_ticket
+            .compare_exchange(
+                serving,
+                serving.wrapping_add(1),
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            )
+            .is_ok()
+            .then_some(TicketLockGuard { lock: self })
+    }
+}
+
+/// Exclusive access to the data of a [`TicketLock`], which is unlocked when dropped.
+pub struct TicketLockGuard<'a, A: Arch, T: ?Sized> {
+    lock: &'a TicketLock<A, T>,
+}
+
+// Safety: The guard only hands out shared references to the data when it is shared.
+unsafe impl<A: Arch, T: ?Sized + Sync> Sync for TicketLockGuard<'_, A, T> {}
+
+impl<A: Arch, T: ?Sized> Deref for TicketLockGuard<'_, A, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // Safety: The lock is held for the lifetime of the guard.
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<A: Arch, T: ?Sized> DerefMut for TicketLockGuard<'_, A, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // Safety: The lock is held for the lifetime of the guard.
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<A: Arch, T: ?Sized> Drop for TicketLockGuard<'_, A, T> {
+    fn drop(&mut self) {
+        // Only the holder of the lock modifies `now_serving`, so this can't race
+        let serving = self.lock.now_serving.load(Ordering::Relaxed);
+        self.lock
+            .now_serving
+            .store(serving.wrapping_add(1), Ordering::Release);
+
+        A::send_event();
+    }
+}
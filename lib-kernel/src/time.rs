@@ -0,0 +1,259 @@
+//! Monotonic time, measured by the clock sources of the board.
+//!
+//! Counter ticks are converted to nanoseconds using only integer arithmetic, as
+//! `(ticks * mult) >> shift`, where `mult` and `shift` are calculated once from the frequency of
+//! the counter. This is exact to well below a nanosecond for any realistic uptime, and avoids any
+//! floating point (which is emulated in software on some targets).
+//!
+//! Boards may have several timers, each of which can be described as a [`ClockSource`] to read
+//! the time from, or as a [`ClockEvent`] to raise interrupts at a deadline. Each is given a
+//! rating, so the kernel can choose the best one available, or compare them against each other.
+
+use core::{
+    fmt,
+    ops::{Add, Sub},
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use crate::{sync::OnceCell, Bsp};
+
+const NANOS_PER_SEC: u64 = 1_000_000_000;
+
+/// A point in time, measured in nanoseconds since the clock source started.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Instant {
+    nanos: u64,
+}
+
+impl Instant {
+    /// The moment that the clock source started.
+    pub const ZERO: Self = Self { nanos: 0 };
+
+    /// Create an instant from the number of nanoseconds since the clock source started.
+    pub const fn from_nanos(nanos: u64) -> Self {
+        Self { nanos }
+    }
+
+    /// Number of nanoseconds since the clock source started.
+    pub const fn as_nanos(self) -> u64 {
+        self.nanos
+    }
+
+    /// Time elapsed from `earlier` to this instant, or zero if `earlier` is later.
+    pub const fn saturating_duration_since(self, earlier: Self) -> Duration {
+        Duration::from_nanos(self.nanos.saturating_sub(earlier.nanos))
+    }
+
+    /// Instant `duration` after this one, or `None` if it can't be represented.
+    pub fn checked_add(self, duration: Duration) -> Option<Self> {
+        let nanos = u64::try_from(duration.as_nanos()).ok()?;
+
+        self.nanos.checked_add(nanos).map(Self::from_nanos)
+    }
+}
+
+impl Add<Duration> for Instant {
+    type Output = Self;
+
+    /// # Panics
+    ///
+    /// Panics if the result can't be represented.
+    fn add(self, duration: Duration) -> Self {
+        self.checked_add(duration)
+            .expect("overflow when adding duration to instant")
+    }
+}
+
+impl Sub for Instant {
+    type Output = Duration;
+
+    /// Time elapsed between two instants, which saturates at zero if `earlier` is later.
+    fn sub(self, earlier: Self) -> Duration {
+        self.saturating_duration_since(earlier)
+    }
+}
+
+/// Displays the number of seconds since the clock source started, to the given precision (or
+/// microseconds by default).
+impl fmt::Display for Instant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let precision = f.precision().unwrap_or(6).min(9);
+        let seconds = self.nanos / NANOS_PER_SEC;
+        let fraction = (self.nanos % NANOS_PER_SEC) / 10u64.pow(9 - precision as u32);
+
+        let width = f
+            .width()
+            .map_or(0, |width| width.saturating_sub(precision + 1));
+
+        if precision == 0 {
+            write!(f, "{seconds:width$}")
+        } else {
+            write!(f, "{seconds:width$}.{fraction:0precision$}")
+        }
+    }
+}
+
+/// Exact conversion between counter ticks and nanoseconds, for a counter of a fixed frequency.
+#[derive(Clone, Copy, Debug)]
+pub struct TickConversion {
+    frequency: u64,
+    mult: u64,
+    shift: u32,
+}
+
+impl TickConversion {
+    /// Calculate the conversion for a counter running at `frequency` Hz.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `frequency` is zero.
+    pub const fn new(frequency: u64) -> Self {
+        assert!(frequency != 0, "counter frequency must not be zero");
+
+        // Use the largest shift for which the multiplier still fits, so as little precision as
+        // possible is lost. As the ticks are at most 64 bits, the product always fits in 128 bits.
+        let mut shift = 63;
+        while (NANOS_PER_SEC as u128) << shift >= (frequency as u128) << 64 {
+            shift -= 1;
+        }
+
+        Self {
+            frequency,
+            mult: (((NANOS_PER_SEC as u128) << shift) / frequency as u128) as u64,
+            shift,
+        }
+    }
+
+    /// Frequency of the counter, in Hz.
+    pub const fn frequency(&self) -> u64 {
+        self.frequency
+    }
+
+    /// Convert a number of counter ticks into nanoseconds, rounding down.
+    pub const fn ticks_to_nanos(&self, ticks: u64) -> u64 {
+        ((ticks as u128 * self.mult as u128) >> self.shift) as u64
+    }
+
+    /// Convert a duration into a number of counter ticks, rounding down and saturating if it
+    /// doesn't fit.
+    pub const fn duration_to_ticks(&self, duration: Duration) -> u64 {
+        let ticks = duration.as_nanos() * self.frequency as u128 / NANOS_PER_SEC as u128;
+
+        if ticks > u64::MAX as u128 {
+            u64::MAX
+        } else {
+            ticks as u64
+        }
+    }
+}
+
+/// Monotonic clock driven by the highest rated clock source of the board.
+///
+/// The source may be read on any core, but reads on different cores may be very slightly out of
+/// step with each other. The clock never returns an instant earlier than one it has already
+/// returned on any core, so time never appears to go backwards when a thread moves between cores.
+pub struct Clock<B: Bsp + 'static> {
+    bsp: &'static B,
+    /// Source chosen the first time it is needed, with the conversion for its frequency.
+    source: OnceCell<B::Arch, (&'static dyn ClockSource, TickConversion)>,
+    /// Latest instant returned on any core, in nanoseconds.
+    latest: AtomicU64,
+}
+
+impl<B: Bsp> Clock<B> {
+    /// Create a new clock for the board `bsp`, which starts from when its source started.
+    pub const fn new(bsp: &'static B) -> Self {
+        Self {
+            bsp,
+            source: OnceCell::new(),
+            latest: AtomicU64::new(0),
+        }
+    }
+
+    /// The source, and its conversion, choosing the source the first time it is needed.
+    fn selected(&self) -> &(&'static dyn ClockSource, TickConversion) {
+        self.source.get_or_init(|| {
+            let source = self
+                .bsp
+                .clock_sources()
+                .max_by_key(|source| source.rating())
+                .expect("board to provide a clock source");
+
+            (source, TickConversion::new(source.frequency()))
+        })
+    }
+
+    /// Clock source that the time is read from.
+    pub fn source(&self) -> &'static dyn ClockSource {
+        self.selected().0
+    }
+
+    /// Conversion for the clock source.
+    pub fn conversion(&self) -> &TickConversion {
+        &self.selected().1
+    }
+
+    /// The current instant.
+    pub fn now(&self) -> Instant {
+        let (source, conversion) = self.selected();
+
+        let nanos = conversion.ticks_to_nanos(source.read());
+        let latest = self.latest.fetch_max(nanos, Ordering::Relaxed);
+
+        Instant::from_nanos(nanos.max(latest))
+    }
+}
+
+/// A free-running counter which can be read to tell the time.
+///
+/// The rating indicates how suitable the source is, where higher is better. As a guide, a rating
+/// of at least 300 indicates a fast, high resolution counter that is synchronised between cores,
+/// whilst 100 to 199 is usable but slow to read or of low resolution.
+pub trait ClockSource: Sync {
+    /// Human readable name of the source, for diagnostics.
+    fn name(&self) -> &'static str;
+
+    /// How suitable the source is for keeping time, where higher is better.
+    fn rating(&self) -> u32;
+
+    /// Frequency of the counter, in Hz.
+    fn frequency(&self) -> u64;
+
+    /// Current value of the counter, which must never decrease.
+    fn read(&self) -> u64;
+}
+
+/// A device which can raise an interrupt once its counter reaches a deadline.
+///
+/// See [`ClockSource`] for the meaning of the rating.
+pub trait ClockEvent: Sync {
+    /// Human readable name of the device, for diagnostics.
+    fn name(&self) -> &'static str;
+
+    /// How suitable the device is for timer interrupts, where higher is better.
+    fn rating(&self) -> u32;
+
+    /// Whether each core has its own instance of the device, in which case it is always the
+    /// instance of the current core that is armed or cancelled.
+    fn per_core(&self) -> bool;
+
+    /// Frequency of the counter that deadlines are measured against, in Hz.
+    fn frequency(&self) -> u64;
+
+    /// Current value of the counter that deadlines are measured against.
+    fn counter(&self) -> u64;
+
+    /// Raise an interrupt once the counter reaches `deadline`, replacing any previous deadline.
+    ///
+    /// Devices which can't raise an interrupt for a deadline that has already passed return
+    /// [`DeadlinePassed`], in which case an interrupt may or may not be raised.
+    fn set_deadline(&self, deadline: u64) -> Result<(), DeadlinePassed>;
+
+    /// Cancel any deadline, so that no further interrupt is raised.
+    fn cancel(&self);
+}
+
+/// Error when a deadline had already passed by the time that a [`ClockEvent`] was armed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DeadlinePassed;
@@ -0,0 +1,130 @@
+//! Inventory of the clocks provided by the board, and cross-checking of them against each other.
+//!
+//! Shortly after boot, every clock source is sampled, and each clock event device which is shared
+//! by all cores is armed to fire after [`CROSS_CHECK_INTERVAL`]. Once it fires, the time that
+//! each source measured is compared against the highest rated source, which reveals any source
+//! with an incorrect frequency, as well as exercising the shared clock event devices.
+
+use core::time::Duration;
+
+use lib_kernel::{
+    time::{ClockEvent, ClockSource, TickConversion},
+    Bsp as _,
+};
+use log::{info, warn};
+
+use crate::{
+    workqueue::{self, Work},
+    IrqSpinMutex, BSP,
+};
+
+/// Maximum number of clock sources that can be cross-checked.
+const MAX_SOURCES: usize = 4;
+
+/// Time after boot at which the clock sources are compared.
+const CROSS_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Difference from the reference source, in parts per million, above which a source is reported.
+const TOLERANCE_PPM: i64 = 1000;
+
+/// Reading of each clock source when the cross-check started.
+static START: IrqSpinMutex<[Option<u64>; MAX_SOURCES]> = IrqSpinMutex::new([None; MAX_SOURCES]);
+
+/// Comparison of the clock sources, deferred out of the interrupt handler of the event device.
+static CROSS_CHECK: Work = Work::new(cross_check);
+
+/// Log the clocks of the board, and start cross-checking the clock sources against each other.
+pub fn init() {
+    for source in BSP.clock_sources() {
+        info!(
+            "Clock source: {} at {} Hz (rating {})",
+            source.name(),
+            source.frequency(),
+            source.rating()
+        );
+    }
+
+    for event in BSP.clock_events() {
+        info!(
+            "Clock event device: {} at {} Hz (rating {}{})",
+            event.name(),
+            event.frequency(),
+            event.rating(),
+            if event.per_core() { ", per core" } else { "" }
+        );
+    }
+
+    {
+        let mut start = START.lock();
+
+        for (reading, source) in start.iter_mut().zip(BSP.clock_sources()) {
+            *reading = Some(source.read());
+        }
+    }
+
+    // Per-core devices are owned by the kernel timers
+    for event in BSP.clock_events().filter(|event| !event.per_core()) {
+        let ticks = TickConversion::new(event.frequency()).duration_to_ticks(CROSS_CHECK_INTERVAL);
+
+        if event.set_deadline(event.counter() + ticks).is_err() {
+            warn!("Clock event device {} could not be armed", event.name());
+        }
+    }
+}
+
+/// Respond to a clock event device which is shared by all cores reaching its deadline.
+pub fn handle_event(_event: &dyn ClockEvent) {
+    workqueue::schedule(&CROSS_CHECK);
+}
+
+/// Compare the time measured by each clock source since [`init`].
+fn cross_check() {
+    let start = *START.lock();
+
+    let elapsed = |source: &dyn ClockSource, start: u64| {
+        let ticks = source.read().wrapping_sub(start);
+
+        TickConversion::new(source.frequency()).ticks_to_nanos(ticks)
+    };
+
+    let Some((reference_index, reference, reference_start)) = BSP
+        .clock_sources()
+        .zip(start)
+        .enumerate()
+        .filter_map(|(index, (source, start))| Some((index, source, start?)))
+        .max_by_key(|(_, source, _)| source.rating())
+    else {
+        return;
+    };
+
+    let reference_elapsed = elapsed(reference, reference_start).max(1);
+
+    for (index, (source, start)) in BSP.clock_sources().zip(start).enumerate() {
+        if index == reference_index {
+            continue;
+        }
+
+        let Some(start) = start else {
+            warn!("Clock source {} was not cross-checked", source.name());
+            continue;
+        };
+
+        let elapsed = elapsed(source, start);
+        let ppm =
+            (elapsed as i64 - reference_elapsed as i64) * 1_000_000 / reference_elapsed as i64;
+
+        if ppm.abs() > TOLERANCE_PPM {
+            warn!(
+                "Clock source {} measured {elapsed}ns, whilst {} measured {reference_elapsed}ns",
+                source.name(),
+                reference.name(),
+            );
+        } else {
+            info!(
+                "Clock source {} agrees with {} to {ppm}ppm",
+                source.name(),
+                reference.name()
+            );
+        }
+    }
+}
@@ -0,0 +1,45 @@
+//! Periodic logging of how busy each core is, from the idle time recorded by the scheduler.
+
+use core::time::Duration;
+
+use lib_kernel::Arch as _;
+use log::info;
+
+use crate::{executor, scheduler, Arch, CLOCK};
+
+/// Time between each report.
+const REPORT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Start reporting the idle time of each core every [`REPORT_INTERVAL`].
+///
+/// # Panics
+///
+/// Panics if the executor has no space for another task.
+pub fn start() {
+    assert!(
+        executor::spawn(report()).is_ok(),
+        "executor to have space for the diagnostics task"
+    );
+}
+
+/// Task which logs the proportion of each interval that every online core spent idle.
+async fn report() {
+    let mut previous = [Duration::ZERO; Arch::MAX_CORES];
+    let mut last = CLOCK.now();
+
+    loop {
+        executor::sleep(REPORT_INTERVAL).await;
+
+        let now = CLOCK.now();
+        let elapsed = (now - last).as_nanos().max(1);
+        last = now;
+
+        for core in (0..Arch::MAX_CORES).filter(|core| scheduler::online().contains(*core)) {
+            let idle = scheduler::idle_time(core);
+            let percent = idle.saturating_sub(previous[core]).as_nanos() * 100 / elapsed;
+            previous[core] = idle;
+
+            info!("Core {core} idle for {percent}% of the last {REPORT_INTERVAL:?}");
+        }
+    }
+}
@@ -0,0 +1,337 @@
+//! Executor for asynchronous kernel tasks, for work that is naturally driven by interrupts.
+//!
+//! Tasks are [`Future`]s which are moved into fixed-size static slots by [`spawn`], and are polled
+//! on a dedicated thread whenever their [`Waker`] is woken. Wakers may be woken from any context,
+//! including interrupt handlers. Whilst no task is ready, the executor thread blocks in a
+//! [`WaitQueue`], leaving the core to other threads or to its idle thread, which sleeps in `wfi`.
+
+use core::{
+    cell::UnsafeCell,
+    future::Future,
+    mem::{self, MaybeUninit},
+    pin::Pin,
+    ptr,
+    sync::atomic::{AtomicBool, AtomicU32, AtomicU8, Ordering},
+    task::{Context, Poll, RawWaker, RawWakerVTable, Waker},
+    time::Duration,
+};
+
+use lib_kernel::time::Instant;
+
+use crate::{
+    scheduler::Priority,
+    sync::WaitQueue,
+    thread,
+    timer::{self, TimerHandle},
+    IrqSpinMutex, CLOCK,
+};
+
+/// Maximum number of tasks that may exist at once.
+const MAX_TASKS: usize = 32;
+
+/// Maximum size of the future of each task.
+const TASK_SIZE: usize = 1024;
+
+/// Maximum alignment of the future of each task.
+const TASK_ALIGN: usize = 16;
+
+/// Maximum number of [`Sleep`] futures that may be waiting at once.
+const MAX_SLEEPERS: usize = 32;
+
+/// The slot is not in use.
+const FREE: u8 = 0;
+/// A future is being moved into the slot.
+const SPAWNING: u8 = 1;
+/// The slot holds a future which hasn't yet completed.
+const ACTIVE: u8 = 2;
+
+/// Storage for every task.
+static TASKS: [TaskSlot; MAX_TASKS] = [const { TaskSlot::new() }; MAX_TASKS];
+
+/// Bit for each task which has been woken, and should be polled.
+static READY: AtomicU32 = AtomicU32::new(0);
+
+/// Whether the thread that polls all tasks has been started.
+static STARTED: AtomicBool = AtomicBool::new(false);
+
+/// Holds the executor thread whilst no task is ready.
+static IDLE: WaitQueue = WaitQueue::new();
+
+/// Wakers of each waiting [`Sleep`], indexed by the argument of its timer.
+static SLEEPERS: IrqSpinMutex<[SleeperSlot; MAX_SLEEPERS]> =
+    IrqSpinMutex::new([const { SleeperSlot::Free }; MAX_SLEEPERS]);
+
+/// Storage for a future, large and aligned enough for any future that may be spawned.
+#[repr(C, align(16))]
+struct TaskStorage([MaybeUninit<u8>; TASK_SIZE]);
+
+/// Functions to operate on the type-erased future of a task.
+struct TaskVTable {
+    poll: unsafe fn(*mut u8, &mut Context<'_>) -> Poll<()>,
+    drop: unsafe fn(*mut u8),
+}
+
+struct TaskSlot {
+    /// One of [`FREE`], [`SPAWNING`] or [`ACTIVE`].
+    state: AtomicU8,
+    future: UnsafeCell<TaskStorage>,
+    vtable: UnsafeCell<Option<&'static TaskVTable>>,
+}
+
+// Safety: The future and vtable are only written by the core which moves the slot out of `FREE`,
+// and are only used by the executor thread once the slot is `ACTIVE`. Spawned futures are `Send`.
+unsafe impl Sync for TaskSlot {}
+
+impl TaskSlot {
+    const fn new() -> Self {
+        Self {
+            state: AtomicU8::new(FREE),
+            future: UnsafeCell::new(TaskStorage([MaybeUninit::uninit(); TASK_SIZE])),
+            vtable: UnsafeCell::new(None),
+        }
+    }
+}
+
+/// Start the executor thread, which will poll all spawned tasks.
+///
+/// # Panics
+///
+/// Panics if the executor has already been started, or no more threads can be created.
+pub fn start() {
+    assert!(
+        !STARTED.swap(true, Ordering::Relaxed),
+        "executor started more than once"
+    );
+
+    thread::spawn(run, Priority::Normal).expect("thread to be available for executor");
+}
+
+/// Add a task which will run `future` to completion.
+///
+/// Returns the future if the maximum number of tasks already exist. Futures larger than
+/// [`TASK_SIZE`], or more aligned than [`TASK_ALIGN`], are rejected at compile time.
+pub fn spawn<F>(future: F) -> Result<(), F>
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    const {
+        assert!(mem::size_of::<F>() <= TASK_SIZE, "future is too large");
+        assert!(mem::align_of::<F>() <= TASK_ALIGN, "future is too aligned");
+    }
+
+    let Some(index) = TASKS.iter().position(|slot| {
+        slot.state
+            .compare_exchange(FREE, SPAWNING, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+    }) else {
+        return Err(future);
+    };
+
+    let slot = &TASKS[index];
+
+    // Safety: The slot was claimed above, so nothing else can access it until it is `ACTIVE`. The
+    // storage is large and aligned enough for `F`, as checked above.
+    unsafe {
+        ptr::write(slot.future.get().cast::<F>(), future);
+        *slot.vtable.get() = Some(&TaskVTable {
+            poll: poll_future::<F>,
+            drop: drop_future::<F>,
+        });
+    }
+
+    slot.state.store(ACTIVE, Ordering::Release);
+    wake_task(index);
+
+    Ok(())
+}
+
+/// Poll the future of type `F` stored at `future`.
+///
+/// # Safety
+///
+/// `future` must point to a valid `F`, which is never moved.
+unsafe fn poll_future<F: Future<Output = ()>>(
+    future: *mut u8,
+    context: &mut Context<'_>,
+) -> Poll<()> {
+    Pin::new_unchecked(&mut *future.cast::<F>()).poll(context)
+}
+
+/// Drop the future of type `F` stored at `future`.
+///
+/// # Safety
+///
+/// `future` must point to a valid `F`, which must not be used again.
+unsafe fn drop_future<F>(future: *mut u8) {
+    ptr::drop_in_place(future.cast::<F>());
+}
+
+/// Mark a task as ready to be polled, and wake the executor.
+fn wake_task(index: usize) {
+    // The executor only needs waking for the first task to become ready, as it polls every ready
+    // task at once
+    if READY.fetch_or(1 << index, Ordering::Release) == 0 {
+        IDLE.wake_one();
+    }
+}
+
+/// Body of the executor thread.
+fn run() {
+    loop {
+        // A task woken whilst the thread is waiting will find it in the queue
+        IDLE.wait_while(|| READY.load(Ordering::Relaxed) == 0);
+
+        let ready = READY.swap(0, Ordering::Acquire);
+
+        (0..MAX_TASKS)
+            .filter(|index| ready & (1 << index) != 0)
+            .for_each(poll_task);
+    }
+}
+
+/// Poll the task in slot `index`, releasing the slot if the task has completed.
+fn poll_task(index: usize) {
+    let slot = &TASKS[index];
+
+    // A stale waker may refer to a task that has since completed
+    if slot.state.load(Ordering::Acquire) != ACTIVE {
+        return;
+    }
+
+    let waker = task_waker(index);
+    let mut context = Context::from_waker(&waker);
+
+    // Safety: The slot is active, so holds a future matching its vtable, and is only ever polled
+    // from the executor thread.
+    unsafe {
+        let vtable = (*slot.vtable.get()).expect("active task to have a vtable");
+        let future = slot.future.get().cast::<u8>();
+
+        if (vtable.poll)(future, &mut context).is_ready() {
+            (vtable.drop)(future);
+            slot.state.store(FREE, Ordering::Release);
+        }
+    }
+}
+
+/// Waker for the task in slot `index`.
+fn task_waker(index: usize) -> Waker {
+    // Safety: The vtable functions treat the data as the index of a task, which is always valid.
+    unsafe { Waker::from_raw(RawWaker::new(index as *const (), &TASK_WAKER_VTABLE)) }
+}
+
+static TASK_WAKER_VTABLE: RawWakerVTable = RawWakerVTable::new(
+    |data| RawWaker::new(data, &TASK_WAKER_VTABLE),
+    |data| wake_task(data as usize),
+    |data| wake_task(data as usize),
+    |_| {},
+);
+
+/// Slot for the waker of a single waiting [`Sleep`].
+enum SleeperSlot {
+    Free,
+    /// Claimed by a [`Sleep`], which will be woken once its timer expires.
+    Waiting(Waker),
+    /// The timer of the [`Sleep`] has expired, and its waker has been woken.
+    Expired,
+}
+
+/// Future which completes once a deadline has passed, created by [`sleep`].
+pub struct Sleep {
+    /// Instant at which the future completes.
+    deadline: Instant,
+    /// Slot in [`SLEEPERS`], and the timer which will wake it, once first polled.
+    timer: Option<(usize, TimerHandle)>,
+}
+
+/// Wait until at least `duration` has elapsed.
+pub fn sleep(duration: Duration) -> Sleep {
+    Sleep {
+        deadline: CLOCK.now() + duration,
+        timer: None,
+    }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<()> {
+        let now = CLOCK.now();
+
+        if now >= self.deadline {
+            return Poll::Ready(());
+        }
+
+        let mut sleepers = SLEEPERS.lock();
+
+        let index = match self.timer {
+            Some((index, _)) => index,
+            None => {
+                let Some(index) = sleepers
+                    .iter()
+                    .position(|slot| matches!(slot, SleeperSlot::Free))
+                else {
+                    // Without a slot, there is no way to be woken, so keep polling instead
+                    context.waker().wake_by_ref();
+                    return Poll::Pending;
+                };
+
+                index
+            }
+        };
+
+        // The timer may expire fractionally before the deadline, so must then be started again
+        let start_timer = self.timer.is_none() || matches!(sleepers[index], SleeperSlot::Expired);
+
+        sleepers[index] = SleeperSlot::Waiting(context.waker().clone());
+
+        if start_timer {
+            match timer::oneshot_with(self.deadline - now, wake_sleeper, index) {
+                Some(handle) => self.timer = Some((index, handle)),
+                None => {
+                    // Try again on the next poll, and keep polling until then. Without a timer
+                    // yet, the slot isn't released on drop, so must be freed now.
+                    sleepers[index] = if self.timer.is_some() {
+                        SleeperSlot::Expired
+                    } else {
+                        SleeperSlot::Free
+                    };
+                    context.waker().wake_by_ref();
+                }
+            }
+        }
+
+        Poll::Pending
+    }
+}
+
+impl Drop for Sleep {
+    fn drop(&mut self) {
+        if let Some((index, handle)) = self.timer.take() {
+            timer::cancel(handle);
+            SLEEPERS.lock()[index] = SleeperSlot::Free;
+        }
+    }
+}
+
+/// Wake the [`Sleep`] waiting in slot `index`, once its timer has expired.
+fn wake_sleeper(index: usize) {
+    let waker = {
+        let mut sleepers = SLEEPERS.lock();
+
+        match mem::replace(&mut sleepers[index], SleeperSlot::Expired) {
+            SleeperSlot::Waiting(waker) => Some(waker),
+            // A stale timer must not claim a slot which has since been freed
+            SleeperSlot::Free => {
+                sleepers[index] = SleeperSlot::Free;
+                None
+            }
+            SleeperSlot::Expired => None,
+        }
+    };
+
+    // Woken without the lock held, as the waker may do anything
+    if let Some(waker) = waker {
+        waker.wake();
+    }
+}
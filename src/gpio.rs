@@ -0,0 +1,145 @@
+//! Interrupts from GPIO pins, dispatched to a handler registered for each pin.
+//!
+//! Mechanical switches bounce between levels for a few milliseconds when pressed or released, so
+//! a pin may be debounced. Its first edge then starts a timer, and further edges are ignored until
+//! it expires. The handler is only called if the pin has settled at the level that the trigger
+//! expects, so a bounce which returns the pin to where it started is ignored entirely.
+//!
+//! Handlers are run from the IRQ handler, so they must be short and must not block.
+
+use core::time::Duration;
+
+use lib_kernel::{
+    gpio::{GpioError, Pull, Trigger},
+    Bsp as _,
+};
+use log::warn;
+
+use crate::{timer, IrqSpinMutex, BSP};
+
+/// Highest number of pins that handlers can be registered for.
+const MAX_PINS: usize = 64;
+
+/// Handler registered for each pin.
+static PINS: [IrqSpinMutex<Option<Registration>>; MAX_PINS] =
+    [const { IrqSpinMutex::new(None) }; MAX_PINS];
+
+struct Registration {
+    trigger: Trigger,
+    /// Time that the pin must be stable for before its handler is called, if it is debounced.
+    debounce: Option<Duration>,
+    /// Function to call with the pin number whenever the trigger occurs.
+    handler: fn(usize),
+    /// Whether a debounce timer is running, during which edges are ignored.
+    settling: bool,
+    /// Level of the pin once it last settled, so that a bounce on both edges can be ignored.
+    high: bool,
+}
+
+/// Call `handler` with the pin number whenever `trigger` occurs on `pin`.
+///
+/// Only edge triggers may be debounced, as a level trigger would continue interrupting whilst it
+/// was being ignored.
+pub fn register(
+    pin: usize,
+    trigger: Trigger,
+    pull: Pull,
+    debounce: Option<Duration>,
+    handler: fn(usize),
+) -> Result<(), GpioError> {
+    if trigger.is_level() && debounce.is_some() {
+        return Err(GpioError::Unsupported);
+    }
+
+    let mut registration = PINS.get(pin).ok_or(GpioError::InvalidPin)?.lock();
+
+    if registration.is_some() {
+        return Err(GpioError::InUse);
+    }
+
+    BSP.enable_gpio_interrupt(pin, trigger, pull)?;
+
+    *registration = Some(Registration {
+        trigger,
+        debounce,
+        handler,
+        settling: false,
+        high: BSP.gpio_is_high(pin).unwrap_or_default(),
+    });
+
+    Ok(())
+}
+
+/// Stop calling the handler registered for `pin`.
+pub fn unregister(pin: usize) {
+    let Some(registration) = PINS.get(pin) else {
+        return;
+    };
+
+    // Any running debounce timer will find nothing registered once it expires
+    if registration.lock().take().is_some() {
+        BSP.disable_gpio_interrupt(pin);
+    }
+}
+
+/// Respond to the trigger of `pin` occurring.
+///
+/// Must be called in response to [`lib_kernel::Interrupt::Gpio`].
+pub fn handle_irq(pin: usize) {
+    let Some(mut guard) = PINS.get(pin).map(IrqSpinMutex::lock) else {
+        return;
+    };
+
+    let Some(registration) = guard.as_mut() else {
+        return;
+    };
+
+    let handler = registration.handler;
+
+    match registration.debounce {
+        Some(_) if registration.settling => return,
+        Some(debounce) => {
+            if timer::oneshot_with(debounce, settle, pin).is_some() {
+                registration.settling = true;
+                return;
+            }
+
+            warn!("No timer available to debounce GPIO pin {pin}");
+        }
+        None => {}
+    }
+
+    // The lock is released first, so the handler may unregister itself
+    drop(guard);
+    handler(pin);
+}
+
+/// Call the handler of `pin` if it has settled at the level its trigger expects, once its
+/// debounce timer has expired.
+fn settle(pin: usize) {
+    let mut guard = PINS[pin].lock();
+
+    let Some(registration) = guard.as_mut() else {
+        return;
+    };
+
+    let high = BSP.gpio_is_high(pin).unwrap_or_default();
+
+    let triggered = match registration.trigger {
+        Trigger::RisingEdge => high,
+        Trigger::FallingEdge => !high,
+        Trigger::BothEdges => high != registration.high,
+        // Level triggers are never debounced
+        Trigger::High | Trigger::Low => false,
+    };
+
+    registration.settling = false;
+    registration.high = high;
+
+    let handler = registration.handler;
+    drop(guard);
+
+    if triggered {
+        handler(pin);
+    }
+}
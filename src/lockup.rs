@@ -0,0 +1,253 @@
+//! Detection of cores which have locked up, either by sitting with interrupts masked (a hard
+//! lockup), or by never switching threads whilst others are waiting to run (a soft lockup).
+//!
+//! Each core updates its heartbeat from a periodic timer, and checks the other cores at the same
+//! time, so detection relies on at least two cores running. Once a core is found to be locked up,
+//! it is sent a non-maskable interrupt to capture its registers and backtrace, which are then
+//! reported by the core that detected it.
+
+use core::{
+    cell::UnsafeCell,
+    sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering},
+    time::Duration,
+};
+
+use lib_kernel::{Arch as _, Bsp as _};
+use log::{error, warn};
+
+use crate::{scheduler, timer, Arch, PerCpu, BSP, CLOCK};
+
+/// Time between each heartbeat, and each check of the other cores.
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Time without a heartbeat after which a core is reported as having interrupts masked.
+const HARD_LOCKUP_THRESHOLD: Duration = Duration::from_secs(5);
+
+/// Time without switching threads, whilst others are waiting, after which a core is reported.
+const SOFT_LOCKUP_THRESHOLD: Duration = Duration::from_secs(10);
+
+/// Whether to panic once a lockup has been reported, rather than letting the system continue.
+const PANIC_ON_LOCKUP: bool = false;
+
+/// Maximum number of return addresses captured from a locked up core.
+const BACKTRACE_DEPTH: usize = 16;
+
+/// Longest time to wait for a locked up core to respond to a non-maskable interrupt.
+const CAPTURE_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// No capture has been requested.
+const IDLE: u8 = 0;
+/// A capture has been requested, and the core has been sent a non-maskable interrupt.
+const REQUESTED: u8 = 1;
+/// The core has written its state, which is waiting to be reported.
+const CAPTURED: u8 = 2;
+
+/// Lockup detection state of each core.
+static CORES: PerCpu<CoreState> = PerCpu::new([const { CoreState::new() }; Arch::MAX_CORES]);
+
+/// Registers of code interrupted by an exception.
+type ExceptionFrame = <Arch as lib_kernel::Arch>::ExceptionFrame;
+
+struct CoreState {
+    /// Instant (in nanoseconds) of the core's last heartbeat.
+    heartbeat: AtomicU64,
+    /// Whether the core has been reported for a hard lockup which hasn't ended yet.
+    hard_reported: AtomicBool,
+    /// Whether the core has been reported for a soft lockup which hasn't ended yet.
+    soft_reported: AtomicBool,
+    /// One of [`IDLE`], [`REQUESTED`] or [`CAPTURED`].
+    capture_state: AtomicU8,
+    /// State captured by the core in response to a non-maskable interrupt.
+    capture: UnsafeCell<Option<Capture>>,
+}
+
+// Safety: The capture is only written by its own core in response to a request (which may since
+// have been withdrawn), and only read by the requesting core once in `CAPTURED`.
+unsafe impl Sync for CoreState {}
+
+impl CoreState {
+    const fn new() -> Self {
+        Self {
+            heartbeat: AtomicU64::new(0),
+            hard_reported: AtomicBool::new(false),
+            soft_reported: AtomicBool::new(false),
+            capture_state: AtomicU8::new(IDLE),
+            capture: UnsafeCell::new(None),
+        }
+    }
+}
+
+/// State of a locked up core, at the point that it was interrupted.
+#[derive(Clone)]
+struct Capture {
+    frame: ExceptionFrame,
+    backtrace: [usize; BACKTRACE_DEPTH],
+    depth: usize,
+}
+
+/// Reason that the state of a locked up core wasn't captured.
+enum CaptureError {
+    /// Another core is already capturing the same core.
+    InProgress,
+    /// The core didn't respond to the non-maskable interrupt within [`CAPTURE_TIMEOUT`].
+    TimedOut,
+}
+
+/// Start the heartbeat of the current core.
+///
+/// # Panics
+///
+/// Panics if the timer queue of the current core is full.
+pub fn init_core() {
+    beat();
+
+    timer::periodic(CHECK_INTERVAL, check).expect("timer queue to have space for the heartbeat");
+}
+
+/// Record that the current core is still servicing interrupts.
+fn beat() {
+    CORES.with(|state| {
+        state
+            .heartbeat
+            .store(CLOCK.now().as_nanos(), Ordering::Relaxed)
+    });
+}
+
+/// Update the heartbeat of the current core, and check every other core for lockups.
+fn check() {
+    beat();
+
+    let current = Arch::core_id();
+    let now = CLOCK.now().as_nanos();
+
+    for core in (0..Arch::MAX_CORES).filter(|core| *core != current) {
+        if !scheduler::online().contains(core) {
+            continue;
+        }
+
+        let state = CORES.get_for(core);
+
+        let heartbeat = state.heartbeat.load(Ordering::Relaxed);
+        let silent = Duration::from_nanos(now.saturating_sub(heartbeat));
+
+        if silent < HARD_LOCKUP_THRESHOLD {
+            state.hard_reported.store(false, Ordering::Relaxed);
+        } else if !state.hard_reported.swap(true, Ordering::Relaxed) {
+            error!("Hard lockup: core {core} hasn't serviced interrupts for {silent:?}");
+            report(core);
+        }
+
+        match scheduler::time_since_switch(core) {
+            Some(running) if running >= SOFT_LOCKUP_THRESHOLD => {
+                if !state.soft_reported.swap(true, Ordering::Relaxed) {
+                    error!("Soft lockup: core {core} hasn't switched threads for {running:?}");
+                    report(core);
+                }
+            }
+            _ => state.soft_reported.store(false, Ordering::Relaxed),
+        }
+    }
+}
+
+/// Report the registers and backtrace of a locked up core, then panic if configured to.
+fn report(core: usize) {
+    match capture(core) {
+        Ok(capture) => {
+            error!("Registers of core {core}:\n{:?}", capture.frame);
+            error!("Backtrace of core {core}:");
+
+            for address in &capture.backtrace[..capture.depth] {
+                error!("  {address:#018x}");
+            }
+        }
+        Err(CaptureError::InProgress) => {
+            warn!("Core {core} is already being captured by another core")
+        }
+        Err(CaptureError::TimedOut) => {
+            warn!("Core {core} didn't respond to a non-maskable interrupt")
+        }
+    }
+
+    if PANIC_ON_LOCKUP {
+        panic!("core {core} locked up");
+    }
+}
+
+/// Interrupt `core` to capture its state, waiting up to [`CAPTURE_TIMEOUT`] for it to respond.
+fn capture(core: usize) -> Result<Capture, CaptureError> {
+    let state = CORES.get_for(core);
+
+    let claim = |current| {
+        state.capture_state.compare_exchange(
+            current,
+            REQUESTED,
+            Ordering::Acquire,
+            Ordering::Relaxed,
+        )
+    };
+
+    match claim(IDLE) {
+        Ok(_) => {}
+        // The core responded after an earlier request timed out, so its capture is stale
+        Err(CAPTURED) if claim(CAPTURED).is_ok() => {
+            // Safety: The core only writes the capture in response to a non-maskable interrupt,
+            // which won't be sent until after this.
+            unsafe { *state.capture.get() = None };
+        }
+        // Another core may already be capturing the same core
+        Err(_) => return Err(CaptureError::InProgress),
+    }
+
+    BSP.send_nmi(core);
+
+    let deadline = CLOCK.now() + CAPTURE_TIMEOUT;
+
+    while state.capture_state.load(Ordering::Acquire) != CAPTURED {
+        // Withdraw the request, so the next one reports the core as unresponsive again rather than
+        // as already being captured. If the core responds later, its capture is discarded as
+        // stale by the next request.
+        if CLOCK.now() >= deadline
+            && state
+                .capture_state
+                .compare_exchange(REQUESTED, IDLE, Ordering::Relaxed, Ordering::Relaxed)
+                .is_ok()
+        {
+            return Err(CaptureError::TimedOut);
+        }
+
+        core::hint::spin_loop();
+    }
+
+    // Safety: The core has finished writing the capture, and won't write it again until it is
+    // requested again.
+    let capture = unsafe { (*state.capture.get()).take() };
+    state.capture_state.store(IDLE, Ordering::Release);
+
+    Ok(capture.expect("captured core to have written its state"))
+}
+
+/// Capture the state of the current core, if it has been requested.
+///
+/// Must be called in response to a non-maskable interrupt, with `frame` as the state of the code
+/// that it interrupted.
+pub fn handle_nmi(frame: &ExceptionFrame) {
+    let state = CORES.get_for(Arch::core_id());
+
+    if state.capture_state.load(Ordering::Acquire) != REQUESTED {
+        return;
+    }
+
+    let mut backtrace = [0; BACKTRACE_DEPTH];
+    let depth = Arch::backtrace(frame, &mut backtrace);
+
+    // Safety: Only this core writes the capture whilst it is requested.
+    unsafe {
+        *state.capture.get() = Some(Capture {
+            frame: frame.clone(),
+            backtrace,
+            depth,
+        });
+    }
+
+    state.capture_state.store(CAPTURED, Ordering::Release);
+}
@@ -1,6 +1,14 @@
-use crate::{Bsp, BSP};
+use core::{
+    fmt::Write,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use crate::{BSP, CLOCK};
 use lib_kernel::Bsp as _;
 
+/// Whether log output should bypass any buffering, and be written synchronously.
+static SYNCHRONOUS: AtomicBool = AtomicBool::new(false);
+
 /// Helper struct to contain all logging-related functionality. Uses the
 /// [`lib_kernel::Bsp::with_debug_console`] method to provide logging to whatever device is most
 /// appropriate.
@@ -13,6 +21,12 @@ impl KernelLogger {
 
         log::set_max_level(log::LevelFilter::Trace);
     }
+
+    /// Write all further log output synchronously. This must be used when buffered output may
+    /// never be transmitted, such as after a panic.
+    pub fn set_synchronous() {
+        SYNCHRONOUS.store(true, Ordering::Relaxed);
+    }
 }
 
 impl log::Log for KernelLogger {
@@ -22,12 +36,12 @@ impl log::Log for KernelLogger {
     }
 
     fn log(&self, record: &log::Record) {
-        let Some(result) = BSP.with_debug_console(|w| {
+        let write = |w: &mut dyn Write| {
             if !self.enabled(record.metadata()) {
                 return Ok(());
             }
 
-            let timestamp = <Bsp as lib_kernel::Bsp>::Arch::uptime().as_secs_f64();
+            let timestamp = CLOCK.now();
 
             writeln!(
                 w,
@@ -35,7 +49,15 @@ impl log::Log for KernelLogger {
                 record.level(),
                 record.args()
             )
-        }) else {
+        };
+
+        let result = if SYNCHRONOUS.load(Ordering::Relaxed) {
+            BSP.with_debug_console_sync(write)
+        } else {
+            BSP.with_debug_console(write)
+        };
+
+        let Some(result) = result else {
             return;
         };
 
@@ -1,49 +1,187 @@
 #![no_std]
 #![no_main]
 
+mod clocks;
+mod diagnostics;
+mod executor;
+mod gpio;
+mod lockup;
 mod logging;
+mod power_button;
+mod scheduler;
+#[cfg(feature = "selftest")]
+mod selftest;
+mod smp;
+mod sync;
+mod thread;
+mod timer;
+mod watchdog;
+mod workqueue;
 
-use crate::logging::KernelLogger;
-use lib_kernel::{Arch as _, Bsp as BspTrait, RawFunction};
-use log::{error, info};
-use rpi3::{Rpi3, Rpi3Config};
-use uom::{fmt::DisplayStyle, si::frequency::megahertz};
+use core::time::Duration;
+
+use crate::{logging::KernelLogger, workqueue::Work};
+use lib_kernel::{
+    ipi::Ipi, time::Clock, Arch as _, Bsp as BspTrait, Interrupt, PanicPolicy, RawFunction,
+};
+use log::{error, info, warn};
+use rpi3::{
+    mailbox::tags::{self, ArmMemory, BoardRevision, BoardSerial, GetClockRate, GetTemperature},
+    Rpi3, Rpi3Config,
+};
 
 /// Configuration object so that a pointer to `kernel_main` can be passed as a type parameter to
 /// the BSP.
 struct Config;
 impl Rpi3Config for Config {
     const KERNEL_MAIN: fn() -> ! = kernel_main;
+    const SECONDARY_MAIN: fn() -> ! = kernel_secondary_main;
+    const IRQ_HANDLER: fn() = kernel_irq;
+    const NMI_HANDLER: fn(&aarch64::ExceptionFrame) = kernel_nmi;
+    const POWER_BUTTON: Option<usize> = Some(3);
 }
 
 /// Type of the BSP used in this compilation.
 type Bsp = Rpi3<Config>;
 
+/// Type of the architecture used in this compilation.
+type Arch = <Bsp as BspTrait>::Arch;
+
+/// Storage for a value on each core of the architecture.
+type PerCpu<T> = lib_kernel::percpu::PerCpu<Arch, T, { Arch::MAX_CORES }>;
+
+/// Spinlock which masks IRQs whilst held, so it can be shared with interrupt handlers.
+type IrqSpinMutex<T> = lib_kernel::sync::IrqSpinMutex<Arch, T>;
+
 /// Instance of the BSP with all of it's state.
 static BSP: Bsp = Bsp::new();
 
-pub static LINKER_FUNCTIONS: &[RawFunction] = <Bsp as BspTrait>::Arch::LINKER_FUNCTIONS;
+/// Monotonic clock shared by all cores.
+static CLOCK: Clock<Bsp> = Clock::new(&BSP);
+
+pub static LINKER_FUNCTIONS: &[RawFunction] = Arch::LINKER_FUNCTIONS;
 
 pub fn kernel_main() -> ! {
     // Ensure the board is initialised.
     BSP.initialise();
+    BSP.initialise_core();
+
+    thread::init_core();
+    lockup::init_core();
 
     // Configure the global logger
     KernelLogger::init();
 
     info!("Kernel starting");
 
-    info!(
-        "Counter running at {}",
-        <Bsp as lib_kernel::Bsp>::Arch::frequency()
-            .into_format_args(megahertz, DisplayStyle::Abbreviation),
-    );
+    info!("Counter running at {} Hz", Arch::counter_frequency());
+
+    log_board_info();
+    clocks::init();
+
+    BSP.start_secondary_cores();
+
+    // Safety: The board is initialised, so all interrupts can be serviced.
+    unsafe { Arch::enable_interrupts() };
+
+    // Logging is deferred out of the interrupt handler
+    static TIMER_RUNNING: Work = Work::new(|| info!("Timer interrupts running"));
+    timer::oneshot(Duration::from_millis(10), || {
+        workqueue::schedule(&TIMER_RUNNING);
+    });
+
+    executor::start();
+    diagnostics::start();
+    watchdog::start();
+    power_button::start();
+    #[cfg(feature = "selftest")]
+    selftest::start();
+
+    // Initialisation is complete, so leave this core to the scheduler
+    thread::exit();
+}
+
+/// Log a description of the board, as reported by its firmware.
+fn log_board_info() {
+    let mailbox = BSP.mailbox();
+
+    match mailbox.request::<BoardRevision>(()) {
+        Ok(revision) => info!("Board revision {revision:#x}"),
+        Err(error) => warn!("Board revision unavailable: {error:?}"),
+    }
+
+    if let Ok(serial) = mailbox.request::<BoardSerial>(()) {
+        info!("Board serial number {serial:016x}");
+    }
+
+    if let Ok(memory) = mailbox.request::<ArmMemory>(()) {
+        info!(
+            "ARM memory: {} MiB at {:#x}",
+            memory.size >> 20,
+            memory.base
+        );
+    }
+
+    if let Ok(clock) = mailbox.request::<GetClockRate>(tags::Clock::Arm) {
+        info!("ARM clock running at {} MHz", clock.rate / 1_000_000);
+    }
+
+    if let Ok(temperature) = mailbox.request::<GetTemperature>(0) {
+        info!(
+            "SoC temperature {}.{:03}°C",
+            temperature.millicelsius / 1000,
+            temperature.millicelsius % 1000
+        );
+    }
+}
+
+/// Entry point for each secondary core, once it has been started by the boot core.
+pub fn kernel_secondary_main() -> ! {
+    BSP.initialise_core();
+    thread::init_core();
+    lockup::init_core();
+
+    info!("Core {} online", Arch::core_id());
 
-    loop {}
+    // Safety: The board was initialised by the boot core, so all interrupts can be serviced.
+    unsafe { Arch::enable_interrupts() };
+
+    thread::exit();
+}
+
+/// Entry point for all IRQs, dispatching each pending interrupt to the relevant subsystem.
+fn kernel_irq() {
+    BSP.handle_irq(|interrupt| match interrupt {
+        Interrupt::Ipi(ipi) => smp::handle_ipi(ipi),
+        // Per-core devices are owned by the timers, which only ever arm the one they chose
+        Interrupt::ClockEvent(event) if event.per_core() => timer::handle_irq(),
+        Interrupt::ClockEvent(event) => clocks::handle_event(event),
+        Interrupt::Gpio(pin) => gpio::handle_irq(pin),
+    });
+
+    workqueue::run_pending_from_irq();
+
+    // Only the outermost handler may switch threads, as the code interrupted by any other is a
+    // handler itself. Deferred work doesn't count, as it runs as though it were ordinary code.
+    if Arch::interrupt_depth() == 1 {
+        scheduler::preempt();
+    }
+}
+
+/// Entry point for non-maskable interrupts, which must not take any locks as the interrupted code
+/// may hold them.
+fn kernel_nmi(frame: &aarch64::ExceptionFrame) {
+    BSP.handle_nmi(|| lockup::handle_nmi(frame));
 }
 
 #[panic_handler]
 fn panic(info: &core::panic::PanicInfo) -> ! {
+    // Stop all other cores, so the system doesn't continue in an inconsistent state
+    smp::broadcast(Ipi::Panic);
+
+    // Interrupts may never be serviced again, so buffered output would be lost
+    KernelLogger::set_synchronous();
+
     error!("==== Panic occurred! ====");
 
     if let Some(location) = info.location() {
@@ -52,5 +190,13 @@ fn panic(info: &core::panic::PanicInfo) -> ! {
 
     error!("{}", info.message());
 
-    loop {}
+    match Config::PANIC_POLICY {
+        PanicPolicy::Halt => {
+            // The watchdog would otherwise reset the board
+            BSP.stop_watchdog();
+            smp::halt();
+        }
+        PanicPolicy::Reboot => BSP.reboot(),
+        PanicPolicy::Shutdown => BSP.shutdown(),
+    }
 }
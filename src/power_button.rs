@@ -0,0 +1,68 @@
+//! Shutdown of the board from a button on the GPIO pin chosen by [`Rpi3Config::POWER_BUTTON`].
+//!
+//! The button connects the pin to ground whilst pressed, so the pin is pulled up and debounced.
+//! Presses are signalled from the GPIO handler to a thread, which shuts the board down outside of
+//! interrupt context.
+
+use core::time::Duration;
+
+use lib_kernel::{
+    gpio::{Pull, Trigger},
+    Bsp as _,
+};
+use log::{info, warn};
+use rpi3::Rpi3Config;
+
+use crate::{
+    gpio, logging::KernelLogger, scheduler::Priority, sync::Semaphore, thread, Config, BSP,
+};
+
+/// Time that the button must stay pressed for before it is acted on.
+const DEBOUNCE: Duration = Duration::from_millis(50);
+
+/// Released by the GPIO handler whenever the button is pressed.
+static PRESSED: Semaphore = Semaphore::new(0);
+
+/// Start listening for presses of the power button, if the board has one.
+///
+/// # Panics
+///
+/// Panics if no more threads can be created.
+pub fn start() {
+    let Some(pin) = Config::POWER_BUTTON else {
+        return;
+    };
+
+    let registered = gpio::register(pin, Trigger::FallingEdge, Pull::Up, Some(DEBOUNCE), pressed);
+
+    if let Err(error) = registered {
+        warn!("Power button on GPIO pin {pin} unavailable: {error:?}");
+        return;
+    }
+
+    thread::spawn(wait, Priority::High).expect("thread to be available for the power button");
+
+    info!("Power button listening on GPIO pin {pin}");
+}
+
+/// Signal the thread that the button has been pressed, from the GPIO handler.
+fn pressed(_pin: usize) {
+    PRESSED.release();
+}
+
+/// Body of the thread which shuts the board down once the button is pressed.
+fn wait() {
+    PRESSED.acquire();
+
+    // Further presses are ignored whilst shutting down
+    if let Some(pin) = Config::POWER_BUTTON {
+        gpio::unregister(pin);
+    }
+
+    // Interrupts stop being serviced once the board shuts down, so buffered output would be lost
+    KernelLogger::set_synchronous();
+
+    info!("Power button pressed, shutting down");
+
+    BSP.shutdown();
+}
@@ -0,0 +1,451 @@
+//! Preemptive, priority-based scheduling of threads across all cores.
+//!
+//! Each core has its own run queue, so cores only contend when moving threads between them. The
+//! ready thread with the highest [`Priority`] on each core always runs, with threads of equal
+//! priority taking turns. Each core has a periodic tick from its timer which ends the time slice
+//! of the current thread, switching to the next ready thread of the same (or higher) priority once
+//! the IRQ handler completes. If there is nothing else to run, each core runs its own idle thread.
+//!
+//! Threads are kept on the core that they last ran on where possible. A core with an empty run
+//! queue steals threads from the busiest core, and every core periodically pulls threads from any
+//! core that is much busier than itself. Threads are only ever run on cores in their [`CpuSet`].
+//!
+//! Whilst a core is idle, its tick is stopped, so the core sleeps until its next timer deadline
+//! (or until it is sent work). The time that each core spends idle is recorded, see
+//! [`idle_time`], and is periodically logged by [`crate::diagnostics`].
+
+use core::{
+    cell::Cell,
+    sync::atomic::{AtomicU32, AtomicU64, AtomicU8, Ordering},
+    time::Duration,
+};
+
+use lib_kernel::{ipi::Ipi, sync::SeqLock, Arch as _, Bsp as _};
+
+use crate::{
+    thread,
+    timer::{self, TimerHandle},
+    Arch, IrqSpinMutex, PerCpu, BSP, CLOCK,
+};
+
+/// Maximum length of time that a thread may run before other threads of the same priority are
+/// given a turn.
+const TIME_SLICE: Duration = Duration::from_millis(10);
+
+/// Number of ticks between each attempt to balance the run queues.
+const BALANCE_INTERVAL: u32 = 10;
+
+/// Maximum number of threads that can be queued on each core.
+const QUEUE_CAPACITY: usize = thread::MAX_THREADS;
+
+/// Threads that are ready to run on each core.
+static RUN_QUEUES: PerCpu<IrqSpinMutex<RunQueue>> =
+    PerCpu::new([const { IrqSpinMutex::new(RunQueue::new()) }; Arch::MAX_CORES]);
+
+/// Priority of the thread running on each core, as encoded by [`rank`], so that other cores can
+/// decide whether a thread they wake should preempt it.
+static RUNNING: PerCpu<AtomicU8> = PerCpu::new([const { AtomicU8::new(0) }; Arch::MAX_CORES]);
+
+/// Whether each core should switch threads at the next opportunity.
+static NEED_RESCHED: PerCpu<Cell<bool>> =
+    PerCpu::new([const { Cell::new(false) }; Arch::MAX_CORES]);
+
+/// Number of ticks on each core since its run queue was last balanced.
+static TICKS: PerCpu<Cell<u32>> = PerCpu::new([const { Cell::new(0) }; Arch::MAX_CORES]);
+
+/// Cores which have started scheduling, as the bits of a [`CpuSet`].
+static ONLINE: AtomicU32 = AtomicU32::new(0);
+
+/// Timer driving the tick of each core, which is only active whilst the core is busy.
+static TICK: PerCpu<Cell<Option<TimerHandle>>> =
+    PerCpu::new([const { Cell::new(None) }; Arch::MAX_CORES]);
+
+/// Time that each core has spent idle, kept consistent so that other cores can read it.
+static IDLE: PerCpu<SeqLock<Arch, IdleTime>> =
+    PerCpu::new([const { SeqLock::new(IdleTime::new()) }; Arch::MAX_CORES]);
+
+/// Instant (in nanoseconds) at which each core last switched threads.
+static LAST_SWITCH: PerCpu<AtomicU64> = PerCpu::new([const { AtomicU64::new(0) }; Arch::MAX_CORES]);
+
+/// Time that a single core has spent idle.
+#[derive(Clone, Copy)]
+struct IdleTime {
+    /// Total nanoseconds spent idle, excluding the current idle period.
+    total: u64,
+    /// Instant (in nanoseconds) at which the core last became idle, or `None` if it is busy.
+    since: Option<u64>,
+}
+
+impl IdleTime {
+    const fn new() -> Self {
+        Self {
+            total: 0,
+            since: None,
+        }
+    }
+}
+
+/// Importance of a thread, where a ready thread will always run in preference to any ready
+/// threads of lower priority.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    Normal,
+    High,
+}
+
+impl Priority {
+    /// Number of priority levels.
+    const COUNT: usize = 2;
+}
+
+/// Order a thread's priority for comparison, where `None` is an idle thread which anything may
+/// preempt.
+fn rank(priority: Option<Priority>) -> u8 {
+    priority.map_or(0, |priority| priority as u8 + 1)
+}
+
+/// Set of cores that a thread is allowed to run on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CpuSet(u32);
+
+impl CpuSet {
+    /// Every core.
+    pub const ALL: Self = Self(u32::MAX);
+
+    /// Only `core`.
+    pub const fn single(core: usize) -> Self {
+        Self(1 << core)
+    }
+
+    /// Determine whether `core` is in the set.
+    pub const fn contains(self, core: usize) -> bool {
+        self.0 & 1 << core != 0
+    }
+
+    /// Cores which are in both this set and `other`.
+    const fn intersection(self, other: Self) -> Self {
+        Self(self.0 & other.0)
+    }
+
+    /// Determine whether the set contains none of the cores of the architecture.
+    pub fn is_empty(self) -> bool {
+        self.cores().next().is_none()
+    }
+
+    /// Iterate over every core in the set.
+    fn cores(self) -> impl Iterator<Item = usize> {
+        (0..Arch::MAX_CORES).filter(move |core| self.contains(*core))
+    }
+}
+
+/// A thread waiting in a run queue.
+#[derive(Clone, Copy)]
+struct QueuedThread {
+    slot: usize,
+    affinity: CpuSet,
+}
+
+/// Threads that are ready to run on a single core, with a queue for each priority.
+struct RunQueue {
+    queues: [ThreadQueue; Priority::COUNT],
+}
+
+impl RunQueue {
+    const fn new() -> Self {
+        Self {
+            queues: [const { ThreadQueue::new() }; Priority::COUNT],
+        }
+    }
+
+    /// Total number of queued threads.
+    fn len(&self) -> usize {
+        self.queues.iter().map(|queue| queue.len).sum()
+    }
+
+    /// Remove the longest waiting thread of the highest priority, only considering threads of at
+    /// least `minimum` priority which may run on `core`. A `minimum` of `None` allows any
+    /// priority.
+    fn pop(&mut self, core: usize, minimum: Option<Priority>) -> Option<usize> {
+        let lowest = minimum.map_or(0, |priority| priority as usize);
+
+        self.queues[lowest..]
+            .iter_mut()
+            .rev()
+            .find_map(|queue| queue.pop(core))
+    }
+}
+
+/// First-in, first-out queue of threads.
+struct ThreadQueue {
+    threads: [Option<QueuedThread>; QUEUE_CAPACITY],
+    len: usize,
+}
+
+impl ThreadQueue {
+    const fn new() -> Self {
+        Self {
+            threads: [None; QUEUE_CAPACITY],
+            len: 0,
+        }
+    }
+
+    fn push(&mut self, thread: QueuedThread) {
+        // Each thread is queued at most once, so the queue can never overflow
+        assert!(self.len < QUEUE_CAPACITY, "thread queued more than once");
+
+        self.threads[self.len] = Some(thread);
+        self.len += 1;
+    }
+
+    /// Remove the longest waiting thread which may run on `core`.
+    fn pop(&mut self, core: usize) -> Option<usize> {
+        let index = self.threads[..self.len]
+            .iter()
+            .flatten()
+            .position(|thread| thread.affinity.contains(core))?;
+        let thread = self.threads[index]?;
+
+        self.threads.copy_within(index + 1..self.len, index);
+        self.len -= 1;
+        self.threads[self.len] = None;
+
+        Some(thread.slot)
+    }
+}
+
+/// Start scheduling on the current core, where a thread of `priority` is running.
+///
+/// # Panics
+///
+/// Panics if the timer queue of the current core is full.
+pub fn init_core(priority: Option<Priority>) {
+    ONLINE.fetch_or(CpuSet::single(Arch::core_id()).0, Ordering::Relaxed);
+
+    // Each core starts out counted as idle, so that the tick starts if the thread isn't idle
+    let now = CLOCK.now().as_nanos();
+    IDLE.with(|idle| idle.write(|idle| idle.since = Some(now)));
+
+    set_running(priority);
+}
+
+/// End the time slice of the current thread, and periodically balance the run queues.
+fn tick() {
+    request_reschedule();
+
+    let balance = TICKS.with(|ticks| {
+        let count = ticks.get() + 1;
+        ticks.set(count % BALANCE_INTERVAL);
+
+        count == BALANCE_INTERVAL
+    });
+
+    if balance {
+        self::balance();
+    }
+}
+
+/// Pull a thread from the busiest core, if it has at least two more queued threads than the
+/// current core.
+fn balance() {
+    let core = Arch::core_id();
+    let length = RUN_QUEUES.get_for(core).lock().len();
+
+    let Some(slot) = steal(core, None, length + 2) else {
+        return;
+    };
+
+    thread::migrate(slot, core);
+}
+
+/// Remove a thread that may run on `core` from the run queue of the busiest other core, as long as
+/// that queue holds at least `threshold` threads. Only threads of at least `minimum` priority are
+/// considered.
+fn steal(core: usize, minimum: Option<Priority>, threshold: usize) -> Option<usize> {
+    let (busiest, length) = (0..Arch::MAX_CORES)
+        .filter(|other| *other != core)
+        .map(|other| (other, RUN_QUEUES.get_for(other).lock().len()))
+        .max_by_key(|(_, length)| *length)?;
+
+    if length < threshold.max(1) {
+        return None;
+    }
+
+    RUN_QUEUES.get_for(busiest).lock().pop(core, minimum)
+}
+
+/// Add a thread to the run queue of a core in `affinity`, preferring `preferred` if it is given
+/// and allowed, or otherwise the least busy core. Returns the chosen core.
+///
+/// # Panics
+///
+/// Panics if `affinity` contains no cores.
+pub fn enqueue(
+    slot: usize,
+    priority: Priority,
+    affinity: CpuSet,
+    preferred: Option<usize>,
+) -> usize {
+    let core = match preferred {
+        Some(preferred) if affinity.contains(preferred) => preferred,
+        _ => {
+            // Threads only allowed on cores which haven't started yet must wait for them
+            let online = affinity.intersection(online());
+            let candidates = if online.is_empty() { affinity } else { online };
+
+            candidates
+                .cores()
+                .min_by_key(|core| RUN_QUEUES.get_for(*core).lock().len())
+                .expect("thread to be allowed to run on at least one core")
+        }
+    };
+
+    requeue(core, slot, priority, affinity);
+
+    core
+}
+
+/// Add a thread to the run queue of `core`, such as one which has been removed from the queue of
+/// another core. If the thread should preempt the thread running on `core`, a reschedule is
+/// requested on it.
+pub fn requeue(core: usize, slot: usize, priority: Priority, affinity: CpuSet) {
+    RUN_QUEUES.get_for(core).lock().queues[priority as usize].push(QueuedThread { slot, affinity });
+
+    if rank(Some(priority)) > RUNNING.get_for(core).load(Ordering::Relaxed) {
+        reschedule(core);
+        return;
+    }
+
+    // Idle cores have no tick to balance with, so must be told that there is work to steal
+    if let Some(idle) = affinity
+        .intersection(online())
+        .cores()
+        .find(|other| *other != core && RUNNING.get_for(*other).load(Ordering::Relaxed) == 0)
+    {
+        reschedule(idle);
+    }
+}
+
+/// Cores which have started scheduling.
+pub fn online() -> CpuSet {
+    CpuSet(ONLINE.load(Ordering::Relaxed))
+}
+
+/// Request a reschedule on `core`, interrupting it if it isn't the current core.
+fn reschedule(core: usize) {
+    if core == Arch::core_id() {
+        request_reschedule();
+    } else {
+        BSP.send_ipi(core, Ipi::Reschedule);
+    }
+}
+
+/// Remove the next thread to run on the current core, of at least `minimum` priority. If the core
+/// has nothing to run, a thread is stolen from another core.
+pub fn pick_next(minimum: Option<Priority>) -> Option<usize> {
+    let core = Arch::core_id();
+
+    // Release the local queue before stealing, as holding it while locking the queues of other
+    // cores could deadlock against a core stealing from this one
+    let local = RUN_QUEUES.get_for(core).lock().pop(core, minimum);
+
+    local.or_else(|| steal(core, minimum, 1))
+}
+
+/// Record the priority of the thread that the current core is switching to, stopping or
+/// restarting the tick if the core is becoming idle or busy.
+///
+/// # Panics
+///
+/// Panics if the timer queue of the current core is full when restarting the tick.
+pub fn set_running(priority: Option<Priority>) {
+    LAST_SWITCH.with(|last| last.store(CLOCK.now().as_nanos(), Ordering::Relaxed));
+
+    let previous = RUNNING.with(|running| running.swap(rank(priority), Ordering::Relaxed));
+
+    match (previous == 0, priority.is_none()) {
+        (true, false) => exit_idle(),
+        (false, true) => enter_idle(),
+        _ => {}
+    }
+}
+
+/// Stop the tick of the current core as it becomes idle, leaving its timer armed for only the
+/// next pending deadline.
+fn enter_idle() {
+    if let Some(tick) = TICK.with(Cell::take) {
+        timer::cancel(tick);
+    }
+
+    let now = CLOCK.now().as_nanos();
+    IDLE.with(|idle| idle.write(|idle| idle.since = Some(now)));
+}
+
+/// Restart the tick of the current core as it stops being idle, and account for the time that it
+/// was idle.
+fn exit_idle() {
+    let now = CLOCK.now().as_nanos();
+
+    // Both are updated together, so a reader never counts the idle period twice or not at all
+    IDLE.with(|idle| {
+        idle.write(|idle| {
+            if let Some(since) = idle.since.take() {
+                idle.total += now.saturating_sub(since);
+            }
+        })
+    });
+
+    let tick = timer::periodic(TIME_SLICE, tick)
+        .expect("timer queue to have space for the scheduler tick");
+    TICK.with(|cell| cell.set(Some(tick)));
+}
+
+/// Total time that `core` has spent idle since it started scheduling.
+pub fn idle_time(core: usize) -> Duration {
+    let idle = IDLE.get_for(core).read();
+
+    let current = idle
+        .since
+        .map_or(0, |since| CLOCK.now().as_nanos().saturating_sub(since));
+
+    Duration::from_nanos(idle.total + current)
+}
+
+/// Time since `core` last switched threads, if it is running a thread whilst others are waiting in
+/// its run queue. Returns `None` if the core is idle, has nothing waiting, or its run queue is
+/// locked (as the core may be stuck holding the lock).
+pub fn time_since_switch(core: usize) -> Option<Duration> {
+    if RUNNING.get_for(core).load(Ordering::Relaxed) == 0 {
+        return None;
+    }
+
+    if RUN_QUEUES.get_for(core).try_lock()?.len() == 0 {
+        return None;
+    }
+
+    let last = LAST_SWITCH.get_for(core).load(Ordering::Relaxed);
+
+    Some(Duration::from_nanos(
+        CLOCK.now().as_nanos().saturating_sub(last),
+    ))
+}
+
+/// Determine whether the current core has been asked to switch threads.
+pub fn reschedule_pending() -> bool {
+    NEED_RESCHED.with(Cell::get)
+}
+
+/// Request that the current core switches threads at the next opportunity, such as when a thread
+/// of higher priority than the current one becomes ready.
+pub fn request_reschedule() {
+    NEED_RESCHED.with(|need| need.set(true));
+}
+
+/// Switch threads if it has been requested on the current core.
+///
+/// Must be called with the current thread in a state where it can be suspended, such as at the end
+/// of the outermost IRQ handler.
+pub fn preempt() {
+    if NEED_RESCHED.with(|need| need.replace(false)) {
+        thread::yield_now();
+    }
+}
@@ -0,0 +1,144 @@
+//! Behaviour checks of the blocking synchronisation primitives, cross-core calls and the executor,
+//! run once at boot so that a regression is reported before anything relies on them.
+//!
+//! Only built with the `selftest` feature. The checks run on their own thread, leaving the boot
+//! core to continue starting the kernel. A failed check panics, so is handled by the board's panic
+//! policy.
+
+use core::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use lib_kernel::Arch as _;
+use log::info;
+
+use crate::{
+    executor,
+    scheduler::{self, Priority},
+    smp,
+    sync::{Condvar, Mutex, Semaphore},
+    thread, Arch,
+};
+
+/// Number of threads contending for [`SHARED`].
+const WORKERS: usize = 2;
+
+/// Number of times that each worker increments the counter of [`SHARED`].
+const INCREMENTS: usize = 100;
+
+/// Time that the executor task sleeps for before signalling [`TASK_DONE`].
+const TASK_SLEEP: Duration = Duration::from_millis(10);
+
+/// State shared between the workers and the checking thread.
+static SHARED: Mutex<Shared> = Mutex::new(Shared {
+    started: false,
+    counter: 0,
+    finished: 0,
+});
+
+/// Notified when the workers should start, and whenever a worker finishes.
+static CHANGED: Condvar = Condvar::new();
+
+/// Released by the executor task once it has slept.
+static TASK_DONE: Semaphore = Semaphore::new(0);
+
+struct Shared {
+    /// Whether the workers may start incrementing.
+    started: bool,
+    counter: usize,
+    /// Number of workers which have finished incrementing.
+    finished: usize,
+}
+
+/// Start the thread which runs every check.
+///
+/// # Panics
+///
+/// Panics if no more threads can be created.
+pub fn start() {
+    thread::spawn(run, Priority::Normal).expect("thread to be available for the self-test");
+}
+
+/// Body of the thread which runs every check.
+fn run() {
+    check_mutex();
+    check_call_on();
+    check_executor();
+
+    info!("Self-test passed");
+}
+
+/// Check that the workers never lose an increment whilst contending for the mutex, and that they
+/// are woken through the condition variable.
+fn check_mutex() {
+    for _ in 0..WORKERS {
+        thread::spawn(worker, Priority::Normal)
+            .expect("thread to be available for a self-test worker");
+    }
+
+    SHARED.lock().started = true;
+    CHANGED.notify_all();
+
+    let shared = CHANGED.wait_while(SHARED.lock(), |shared| shared.finished < WORKERS);
+
+    assert_eq!(
+        shared.counter,
+        WORKERS * INCREMENTS,
+        "mutex lost increments"
+    );
+}
+
+/// Body of each worker of [`check_mutex`].
+fn worker() {
+    drop(CHANGED.wait_while(SHARED.lock(), |shared| !shared.started));
+
+    for _ in 0..INCREMENTS {
+        let mut shared = SHARED.lock();
+        let counter = shared.counter;
+
+        // Give the other worker a chance to run whilst the lock is held
+        thread::yield_now();
+
+        shared.counter = counter + 1;
+    }
+
+    SHARED.lock().finished += 1;
+    CHANGED.notify_one();
+}
+
+/// Check that a cross-core call runs on the requested core.
+fn check_call_on() {
+    let current = Arch::core_id();
+
+    let Some(other) =
+        (0..Arch::MAX_CORES).find(|core| *core != current && scheduler::online().contains(*core))
+    else {
+        return;
+    };
+
+    let ran_on = AtomicUsize::new(usize::MAX);
+    smp::call_on(other, || ran_on.store(Arch::core_id(), Ordering::Relaxed));
+
+    assert_eq!(
+        ran_on.load(Ordering::Relaxed),
+        other,
+        "cross-core call ran on the wrong core"
+    );
+}
+
+/// Check that a task on the executor can sleep, and then wake a blocked thread.
+fn check_executor() {
+    let spawned = executor::spawn(async {
+        executor::sleep(TASK_SLEEP).await;
+        TASK_DONE.release();
+    });
+    assert!(spawned.is_ok(), "executor to have space for a task");
+
+    TASK_DONE.acquire();
+
+    assert!(
+        !TASK_DONE.try_acquire(),
+        "semaphore permit released more than once"
+    );
+}
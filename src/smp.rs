@@ -0,0 +1,49 @@
+//! Coordination between cores, using inter-processor interrupts.
+
+use lib_kernel::{ipi::CrossCall, ipi::Ipi, Arch as _, Bsp as _};
+
+use crate::{scheduler, Arch, BSP};
+
+/// Requests for closures to run on other cores.
+static CROSS_CALL: CrossCall<Arch, { Arch::MAX_CORES }> = CrossCall::new();
+
+/// Run `f` on `core`, waiting until it has completed.
+// Nothing runs code on another core yet, other than the self-test
+#[cfg_attr(not(feature = "selftest"), expect(dead_code))]
+pub fn call_on<F>(core: usize, f: F)
+where
+    F: Fn() + Sync,
+{
+    CROSS_CALL.call_on(&BSP, core, f);
+}
+
+/// Send `ipi` to every core other than the current one.
+pub fn broadcast(ipi: Ipi) {
+    let current = Arch::core_id();
+
+    (0..Arch::MAX_CORES)
+        .filter(|core| *core != current)
+        .for_each(|core| BSP.send_ipi(core, ipi));
+}
+
+/// Respond to a message sent by another core.
+pub fn handle_ipi(ipi: Ipi) {
+    match ipi {
+        // The switch happens once the IRQ handler completes
+        Ipi::Reschedule => scheduler::request_reschedule(),
+        Ipi::TlbShootdown => Arch::invalidate_tlb(),
+        Ipi::CallFunction => CROSS_CALL.handle(),
+        Ipi::Panic => halt(),
+        // The work runs once the IRQ handler completes
+        Ipi::Work => {}
+    }
+}
+
+/// Stop the current core permanently.
+pub fn halt() -> ! {
+    Arch::disable_interrupts();
+
+    loop {
+        Arch::wait_for_interrupt();
+    }
+}
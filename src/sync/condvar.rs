@@ -0,0 +1,67 @@
+// Nothing blocks on a condition variable yet, other than the self-test
+#![cfg_attr(not(feature = "selftest"), expect(dead_code))]
+
+use super::{MutexGuard, WaitQueue};
+use crate::thread;
+
+/// Condition variable, allowing threads to block until another thread signals that the data
+/// protected by a [`super::Mutex`] has changed.
+pub struct Condvar {
+    waiters: WaitQueue,
+}
+
+impl Condvar {
+    /// Create a new condition variable, with no waiting threads.
+    pub const fn new() -> Self {
+        Self {
+            waiters: WaitQueue::new(),
+        }
+    }
+
+    /// Release the mutex of `guard` and block until notified, then take the mutex again.
+    ///
+    /// This may also return spuriously, so callers must re-check whatever they are waiting for,
+    /// or use [`Condvar::wait_while`].
+    pub fn wait<'a, T: ?Sized>(&self, guard: MutexGuard<'a, T>) -> MutexGuard<'a, T> {
+        let mutex = guard.mutex();
+
+        // Start waiting before the mutex is released, so a notification can't be missed
+        self.waiters.prepare_to_wait();
+        drop(guard);
+
+        thread::block();
+        self.waiters.finish_wait();
+
+        mutex.lock()
+    }
+
+    /// Block for as long as `condition` returns `true` for the data protected by `guard`,
+    /// releasing the mutex whilst blocked.
+    pub fn wait_while<'a, T: ?Sized>(
+        &self,
+        mut guard: MutexGuard<'a, T>,
+        mut condition: impl FnMut(&mut T) -> bool,
+    ) -> MutexGuard<'a, T> {
+        while condition(&mut guard) {
+            guard = self.wait(guard);
+        }
+
+        guard
+    }
+
+    /// Wake the thread that has been waiting the longest.
+    pub fn notify_one(&self) {
+        self.waiters.wake_one();
+    }
+
+    /// Wake all waiting threads.
+    pub fn notify_all(&self) {
+        self.waiters.wake_all();
+    }
+}
+
+impl Default for Condvar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
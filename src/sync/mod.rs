@@ -0,0 +1,17 @@
+//! Blocking synchronisation primitives for kernel threads.
+//!
+//! Unlike the spinlocks in [`lib_kernel::sync`], waiting threads are suspended so that other
+//! threads can run. Waiting must only ever happen on a kernel thread, but waking (such as
+//! [`Semaphore::release`] or [`WaitQueue::wake_one`]) may also be done from interrupt handlers.
+
+mod condvar;
+mod mutex;
+mod semaphore;
+mod wait_queue;
+
+#[cfg_attr(not(feature = "selftest"), expect(unused_imports))]
+pub use condvar::Condvar;
+#[cfg_attr(not(feature = "selftest"), expect(unused_imports))]
+pub use mutex::{Mutex, MutexGuard};
+pub use semaphore::Semaphore;
+pub use wait_queue::WaitQueue;
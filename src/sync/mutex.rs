@@ -0,0 +1,99 @@
+// Nothing takes a blocking mutex yet, other than the self-test
+#![cfg_attr(not(feature = "selftest"), expect(dead_code))]
+
+use core::{
+    cell::UnsafeCell,
+    ops::{Deref, DerefMut},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use super::WaitQueue;
+
+/// Mutual exclusion lock, which blocks the current thread whilst waiting for it.
+///
+/// This must only be used from kernel threads, and is best suited to data which may be held for a
+/// long time. Data shared with interrupt handlers must use [`crate::IrqSpinMutex`] instead.
+pub struct Mutex<T: ?Sized> {
+    locked: AtomicBool,
+    /// Threads waiting for the lock to be released.
+    waiters: WaitQueue,
+    data: UnsafeCell<T>,
+}
+
+// Safety: Access to the data is serialised by the lock.
+unsafe impl<T: ?Sized + Send> Sync for Mutex<T> {}
+unsafe impl<T: ?Sized + Send> Send for Mutex<T> {}
+
+impl<T> Mutex<T> {
+    /// Create a new, unlocked mutex.
+    pub const fn new(data: T) -> Self {
+        Self {
+            locked: AtomicBool::new(false),
+            waiters: WaitQueue::new(),
+            data: UnsafeCell::new(data),
+        }
+    }
+}
+
+impl<T: ?Sized> Mutex<T> {
+    /// Take the lock, blocking until it is available.
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, panics if called from interrupt context.
+    pub fn lock(&self) -> MutexGuard<'_, T> {
+        let mut guard = None;
+        self.waiters.wait_while(|| {
+            guard = self.try_lock();
+            guard.is_none()
+        });
+
+        guard.expect("lock to be taken once waiting has finished")
+    }
+
+    /// Take the lock if it is available, without blocking.
+    pub fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+        self.locked
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+            .then_some(MutexGuard { mutex: self })
+    }
+}
+
+/// Exclusive access to the data of a [`Mutex`], which is unlocked when dropped.
+pub struct MutexGuard<'a, T: ?Sized> {
+    mutex: &'a Mutex<T>,
+}
+
+// Safety: The guard only hands out shared references to the data when it is shared.
+unsafe impl<T: ?Sized + Sync> Sync for MutexGuard<'_, T> {}
+
+impl<'a, T: ?Sized> MutexGuard<'a, T> {
+    /// Mutex that this guard belongs to.
+    pub(super) fn mutex(&self) -> &'a Mutex<T> {
+        self.mutex
+    }
+}
+
+impl<T: ?Sized> Deref for MutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // Safety: The lock is held for the lifetime of the guard.
+        unsafe { &*self.mutex.data.get() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for MutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // Safety: The lock is held for the lifetime of the guard.
+        unsafe { &mut *self.mutex.data.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for MutexGuard<'_, T> {
+    fn drop(&mut self) {
+        self.mutex.locked.store(false, Ordering::Release);
+        self.mutex.waiters.wake_one();
+    }
+}
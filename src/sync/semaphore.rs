@@ -0,0 +1,48 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use super::WaitQueue;
+
+/// Counting semaphore, where threads block until a permit is available.
+///
+/// Permits may be released from interrupt handlers, making this suitable for signalling a thread
+/// that a device needs attention.
+pub struct Semaphore {
+    permits: AtomicUsize,
+    /// Threads waiting for a permit to be released.
+    waiters: WaitQueue,
+}
+
+impl Semaphore {
+    /// Create a new semaphore with `permits` available.
+    pub const fn new(permits: usize) -> Self {
+        Self {
+            permits: AtomicUsize::new(permits),
+            waiters: WaitQueue::new(),
+        }
+    }
+
+    /// Take a permit, blocking until one is available.
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, panics if called from interrupt context.
+    pub fn acquire(&self) {
+        self.waiters.wait_while(|| !self.try_acquire());
+    }
+
+    /// Take a permit if one is available, without blocking.
+    pub fn try_acquire(&self) -> bool {
+        self.permits
+            .fetch_update(Ordering::Acquire, Ordering::Relaxed, |permits| {
+                permits.checked_sub(1)
+            })
+            .is_ok()
+    }
+
+    /// Return a permit, waking a waiting thread if there is one. May be called from interrupt
+    /// context.
+    pub fn release(&self) {
+        self.permits.fetch_add(1, Ordering::Release);
+        self.waiters.wake_one();
+    }
+}
@@ -0,0 +1,135 @@
+use lib_kernel::Arch as _;
+
+use crate::{
+    thread::{self, ThreadId, MAX_THREADS},
+    Arch, IrqSpinMutex,
+};
+
+/// Queue of threads waiting for something to happen, which are woken in the order that they
+/// started waiting.
+pub struct WaitQueue {
+    waiters: IrqSpinMutex<Waiters>,
+}
+
+impl WaitQueue {
+    /// Create a new, empty queue.
+    pub const fn new() -> Self {
+        Self {
+            waiters: IrqSpinMutex::new(Waiters::new()),
+        }
+    }
+
+    /// Block the current thread for as long as `condition` returns `true`, re-checking it
+    /// whenever the thread is woken.
+    ///
+    /// The thread is added to the queue before each check, so a wake-up between the check and the
+    /// thread blocking is never missed.
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, panics if called from interrupt context.
+    pub fn wait_while(&self, mut condition: impl FnMut() -> bool) {
+        debug_assert!(
+            !Arch::in_interrupt(),
+            "wait queue used in interrupt context"
+        );
+
+        loop {
+            self.prepare_to_wait();
+
+            if !condition() {
+                break;
+            }
+
+            thread::block();
+        }
+
+        self.finish_wait();
+    }
+
+    /// Add the current thread to the queue, if it isn't already waiting, so that it will be woken
+    /// by a following [`WaitQueue::wake_one`] or [`WaitQueue::wake_all`]. The thread should then
+    /// block with [`thread::block`], and call [`WaitQueue::finish_wait`] once it stops waiting.
+    pub fn prepare_to_wait(&self) {
+        self.waiters.lock().push(thread::current());
+    }
+
+    /// Remove the current thread from the queue, if it wasn't removed by being woken.
+    pub fn finish_wait(&self) {
+        self.waiters.lock().remove(thread::current());
+    }
+
+    /// Wake the thread that has been waiting the longest. Returns `false` if there were no
+    /// waiting threads.
+    pub fn wake_one(&self) -> bool {
+        let waiter = self.waiters.lock().pop();
+
+        waiter.map(thread::wake).is_some()
+    }
+
+    /// Wake all waiting threads, returning the number woken.
+    pub fn wake_all(&self) -> usize {
+        let mut woken = 0;
+
+        // Threads are woken one at a time, so the lock isn't held whilst waking
+        while self.wake_one() {
+            woken += 1;
+        }
+
+        woken
+    }
+}
+
+impl Default for WaitQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Threads in a [`WaitQueue`], in the order that they started waiting.
+struct Waiters {
+    threads: [Option<ThreadId>; MAX_THREADS],
+    len: usize,
+}
+
+impl Waiters {
+    const fn new() -> Self {
+        Self {
+            threads: [None; MAX_THREADS],
+            len: 0,
+        }
+    }
+
+    /// Add a thread to the back of the queue, unless it is already in the queue.
+    fn push(&mut self, id: ThreadId) {
+        if self.threads[..self.len].contains(&Some(id)) {
+            return;
+        }
+
+        // Each thread can only be waiting once, so the queue can never overflow
+        self.threads[self.len] = Some(id);
+        self.len += 1;
+    }
+
+    /// Remove the thread at the front of the queue.
+    fn pop(&mut self) -> Option<ThreadId> {
+        let id = self.threads[..self.len].first().copied().flatten()?;
+        self.remove(id);
+
+        Some(id)
+    }
+
+    /// Remove a thread from anywhere in the queue.
+    fn remove(&mut self, id: ThreadId) {
+        let Some(index) = self.threads[..self.len]
+            .iter()
+            .position(|waiter| *waiter == Some(id))
+        else {
+            return;
+        };
+
+        self.threads.copy_within(index + 1..self.len, index);
+        self.len -= 1;
+        self.threads[self.len] = None;
+    }
+}
@@ -0,0 +1,497 @@
+//! Kernel threads, each with their own stack, which are scheduled onto the cores.
+//!
+//! Each core adopts the code that it booted into as its first thread, and has an idle thread which
+//! runs whenever there is nothing else to do. Further threads are created with [`spawn`], and run
+//! until they are preempted, [`yield_now`], [`block`] or [`exit`]. Each thread has its own lock,
+//! so cores only contend when operating on the same thread. A thread may resume on a different
+//! core to the one that it was suspended on, as long as the core is in the thread's [`CpuSet`].
+//! See [`crate::scheduler`] for how the next thread is chosen.
+
+use core::{
+    cell::{Cell, UnsafeCell},
+    ops::Range,
+    time::Duration,
+};
+
+use lib_kernel::{sync::IrqSpinMutexGuard, without_interrupts, Arch as _};
+
+use crate::{
+    scheduler::{self, CpuSet, Priority},
+    timer, Arch, IrqSpinMutex, PerCpu,
+};
+
+/// Maximum number of threads that may exist at once, including the threads of each core.
+pub const MAX_THREADS: usize = 32;
+
+/// Size of the stack of each spawned thread.
+const STACK_SIZE: usize = 16 * 1024;
+
+/// Saved state of a suspended thread.
+type Context = <Arch as lib_kernel::Arch>::ThreadContext;
+
+/// Control block of each thread.
+static THREADS: [IrqSpinMutex<Thread>; MAX_THREADS] =
+    [const { IrqSpinMutex::new(Thread::new()) }; MAX_THREADS];
+
+/// Context and stack of each thread, indexed by the slot of the thread in [`THREADS`].
+static SLOTS: [ThreadSlot; MAX_THREADS] = [const { ThreadSlot::new() }; MAX_THREADS];
+
+/// Slot of the thread running on each core.
+static CURRENT: PerCpu<Cell<Option<usize>>> =
+    PerCpu::new([const { Cell::new(None) }; Arch::MAX_CORES]);
+
+/// Slot of the idle thread of each core.
+static IDLE: PerCpu<Cell<Option<usize>>> =
+    PerCpu::new([const { Cell::new(None) }; Arch::MAX_CORES]);
+
+/// Slot of the thread that each core has just switched away from, which is finished off by the
+/// next thread once the switch is complete.
+static PREVIOUS: PerCpu<Cell<Option<usize>>> =
+    PerCpu::new([const { Cell::new(None) }; Arch::MAX_CORES]);
+
+/// Identifier of a thread.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ThreadId {
+    /// Slot in the table that the thread occupies.
+    slot: usize,
+    /// Generation of the slot when the thread was created, so a stale identifier cannot refer to a
+    /// different thread that later re-uses the slot.
+    generation: u64,
+}
+
+impl ThreadId {
+    /// Index of the slot that the thread occupies, which is below [`MAX_THREADS`] and unique
+    /// amongst the threads that currently exist.
+    pub fn index(self) -> usize {
+        self.slot
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+    /// The slot is not in use.
+    Free,
+    /// Able to run, and waiting in a run queue (or about to be added once its core has switched
+    /// away from it, or whilst it is moved between cores).
+    Ready,
+    /// Currently running on a core.
+    Running,
+    /// Waiting to be woken by [`wake`].
+    Blocked,
+    /// Finished, and waiting for its slot to be released once no core is using its stack.
+    Exited,
+}
+
+/// Control block of a single thread.
+struct Thread {
+    state: State,
+    /// Priority of the thread, or `None` for an idle thread, which is never queued.
+    priority: Option<Priority>,
+    /// Cores that the thread may run on.
+    affinity: CpuSet,
+    /// Core that the thread last ran on, or is queued on.
+    core: usize,
+    /// Function to run when the thread starts, or `None` for the thread each core booted into.
+    entry: Option<fn()>,
+    generation: u64,
+    /// Whether the thread was woken whilst it wasn't blocked, so the next call to [`block`] should
+    /// return immediately.
+    wake_pending: bool,
+    /// Whether a core is part way through switching away from the thread, so it can't be queued
+    /// yet.
+    switching: bool,
+}
+
+impl Thread {
+    const fn new() -> Self {
+        Self {
+            state: State::Free,
+            priority: None,
+            affinity: CpuSet::ALL,
+            core: 0,
+            entry: None,
+            generation: 0,
+            wake_pending: false,
+            switching: false,
+        }
+    }
+
+    /// Add the thread to a run queue, preferring `preferred` (if any) when choosing the core.
+    fn make_ready(&mut self, slot: usize, preferred: Option<usize>) {
+        self.state = State::Ready;
+
+        // Idle threads only run when there is nothing in the queue
+        let Some(priority) = self.priority else {
+            return;
+        };
+
+        self.core = scheduler::enqueue(slot, priority, self.affinity, preferred);
+    }
+}
+
+/// Parts of a thread that are used whilst switching, so can't be protected by the thread's lock.
+struct ThreadSlot {
+    context: UnsafeCell<Context>,
+    stack: Stack,
+}
+
+// Safety: The context is only accessed by the core switching to or from the thread, which is
+// serialised by the thread's state. The stack is only used by the thread itself.
+unsafe impl Sync for ThreadSlot {}
+
+impl ThreadSlot {
+    const fn new() -> Self {
+        Self {
+            context: UnsafeCell::new(Arch::EMPTY_THREAD_CONTEXT),
+            stack: Stack(UnsafeCell::new([0; STACK_SIZE])),
+        }
+    }
+
+    /// Prepare the context of a newly allocated thread, so that it will start by running its entry.
+    fn initialise_context(&self, slot: usize) {
+        let context = Arch::new_thread_context(self.stack.range(), thread_start, slot);
+
+        // Safety: The slot was free, so nothing else can be accessing its context.
+        unsafe { *self.context.get() = context };
+    }
+}
+
+#[repr(C, align(16))]
+struct Stack(UnsafeCell<[u8; STACK_SIZE]>);
+
+impl Stack {
+    /// Addresses of the stack, which grows downwards from the end.
+    fn range(&self) -> Range<usize> {
+        let start = self.0.get() as usize;
+
+        start..start + STACK_SIZE
+    }
+}
+
+/// Claim a free slot for a new thread, returning `None` if there are none. The thread is returned
+/// locked, so that it can be prepared before anything else can use it.
+fn allocate(
+    priority: Option<Priority>,
+    affinity: CpuSet,
+    entry: Option<fn()>,
+) -> Option<(ThreadId, IrqSpinMutexGuard<'static, Arch, Thread>)> {
+    THREADS.iter().enumerate().find_map(|(slot, thread)| {
+        let mut thread = thread.lock();
+
+        if thread.state != State::Free {
+            return None;
+        }
+
+        thread.priority = priority;
+        thread.affinity = affinity;
+        thread.core = Arch::core_id();
+        thread.entry = entry;
+        thread.generation += 1;
+        thread.wake_pending = false;
+
+        let id = ThreadId {
+            slot,
+            generation: thread.generation,
+        };
+
+        Some((id, thread))
+    })
+}
+
+/// Adopt the code running on the current core as a thread, create the idle thread of the core,
+/// and start scheduling on it.
+///
+/// Must be called once on each core before any other function in this module is used.
+///
+/// # Panics
+///
+/// Panics if the maximum number of threads already exist.
+pub fn init_core() {
+    let core = Arch::core_id();
+
+    let (id, mut thread) = allocate(Some(Priority::Normal), CpuSet::ALL, None)
+        .expect("thread table to have space for every core");
+    thread.state = State::Running;
+    drop(thread);
+    CURRENT.with(|current| current.set(Some(id.slot)));
+
+    let (idle, mut thread) = allocate(None, CpuSet::single(core), Some(idle))
+        .expect("thread table to have space for every core");
+    SLOTS[idle.slot].initialise_context(idle.slot);
+    thread.state = State::Ready;
+    drop(thread);
+    IDLE.with(|current| current.set(Some(idle.slot)));
+
+    scheduler::init_core(Some(Priority::Normal));
+}
+
+/// Create a new thread which runs `entry` at `priority`, and exits once it returns.
+///
+/// Returns `None` if the maximum number of threads already exist.
+pub fn spawn(entry: fn(), priority: Priority) -> Option<ThreadId> {
+    spawn_on(entry, priority, CpuSet::ALL)
+}
+
+/// Create a new thread which runs `entry` at `priority` on the cores in `affinity`, and exits once
+/// it returns.
+///
+/// Returns `None` if the maximum number of threads already exist.
+///
+/// # Panics
+///
+/// Panics if `affinity` contains none of the cores.
+pub fn spawn_on(entry: fn(), priority: Priority, affinity: CpuSet) -> Option<ThreadId> {
+    assert!(
+        !affinity.is_empty(),
+        "thread must be allowed to run on a core"
+    );
+
+    let id = {
+        let (id, mut thread) = allocate(Some(priority), affinity, Some(entry))?;
+
+        SLOTS[id.slot].initialise_context(id.slot);
+        thread.make_ready(id.slot, None);
+
+        id
+    };
+
+    preempt_if_possible();
+
+    Some(id)
+}
+
+/// Identifier of the current thread.
+pub fn current() -> ThreadId {
+    let slot = current_slot();
+
+    ThreadId {
+        slot,
+        generation: THREADS[slot].lock().generation,
+    }
+}
+
+/// Let any other ready thread of the same or higher priority run. Returns immediately if there
+/// are none.
+pub fn yield_now() {
+    switch(State::Ready);
+}
+
+/// Suspend the current thread until it is woken with [`wake`]. If it has been woken since it last
+/// blocked, this returns immediately instead.
+///
+/// This may also return spuriously, so callers must re-check whatever they are waiting for.
+///
+/// # Panics
+///
+/// Panics if called by an idle thread.
+pub fn block() {
+    switch(State::Blocked);
+}
+
+/// Wake a blocked thread. If the thread isn't blocked, its next call to [`block`] will return
+/// immediately instead. Has no effect if the thread has exited.
+///
+/// The thread is queued on the core it last ran on, which is interrupted if the thread should
+/// preempt whatever it is running. May be called from interrupt context.
+pub fn wake(id: ThreadId) {
+    {
+        let mut thread = THREADS[id.slot].lock();
+
+        if thread.generation != id.generation {
+            return;
+        }
+
+        match thread.state {
+            // The core switching away from the thread will queue it once it is suspended
+            State::Blocked if thread.switching => thread.state = State::Ready,
+            State::Blocked => {
+                let core = thread.core;
+                thread.make_ready(id.slot, Some(core));
+            }
+            State::Ready | State::Running => thread.wake_pending = true,
+            State::Free | State::Exited => {}
+        }
+    }
+
+    preempt_if_possible();
+}
+
+/// Move a ready thread, which has just been removed from the run queue of another core, to the run
+/// queue of `core`.
+pub fn migrate(slot: usize, core: usize) {
+    let mut thread = THREADS[slot].lock();
+    let priority = thread
+        .priority
+        .expect("idle thread to never be in a run queue");
+
+    thread.core = core;
+    scheduler::requeue(core, slot, priority, thread.affinity);
+}
+
+/// Suspend the current thread until at least `duration` has elapsed.
+///
+/// # Panics
+///
+/// Panics if called by an idle thread.
+pub fn sleep(duration: Duration) {
+    let deadline = timer::counter() + timer::duration_to_ticks(duration);
+
+    let Some(handle) = timer::oneshot_with(duration, wake_sleeper, current_slot()) else {
+        // Without a timer to wake this thread, let others run until the deadline has passed
+        while timer::counter() < deadline {
+            yield_now();
+        }
+
+        return;
+    };
+
+    while timer::counter() < deadline {
+        block();
+    }
+
+    timer::cancel(handle);
+}
+
+/// Wake the thread in `slot`, once its sleep has finished.
+fn wake_sleeper(slot: usize) {
+    let generation = THREADS[slot].lock().generation;
+
+    wake(ThreadId { slot, generation });
+}
+
+/// Finish the current thread, never to run again.
+///
+/// # Panics
+///
+/// Panics if called by an idle thread.
+pub fn exit() -> ! {
+    switch(State::Exited);
+
+    unreachable!("exited thread to never be resumed");
+}
+
+fn current_slot() -> usize {
+    CURRENT
+        .with(Cell::get)
+        .expect("core to have been adopted as a thread")
+}
+
+/// Switch threads now if a reschedule has been requested, and it is safe to do so.
+fn preempt_if_possible() {
+    // Masked interrupts may mean that a lock is held, which the next thread could need
+    if Arch::interrupts_enabled() && !Arch::in_interrupt() {
+        scheduler::preempt();
+    }
+}
+
+/// Switch from the current thread to the next thread to run, leaving the current thread in
+/// `state` once the switch is complete.
+fn switch(state: State) {
+    without_interrupts::<Arch, _, _>(|| {
+        let previous = current_slot();
+        let core = Arch::core_id();
+
+        let next = {
+            let mut thread = THREADS[previous].lock();
+
+            assert!(
+                thread.priority.is_some() || state == State::Ready,
+                "idle thread must never block or exit"
+            );
+
+            if state == State::Blocked && thread.wake_pending {
+                thread.wake_pending = false;
+                return;
+            }
+
+            // Yielding only gives way to threads that would be allowed to preempt this one, unless
+            // the thread is no longer allowed to run on this core
+            let stay = state == State::Ready && thread.affinity.contains(core);
+            let minimum = if stay { thread.priority } else { None };
+
+            let next = match scheduler::pick_next(minimum) {
+                Some(next) => next,
+                None if stay => return,
+                None => IDLE.with(Cell::get).expect("core to have an idle thread"),
+            };
+
+            // The previous thread is only marked, and not queued or released, until the switch is
+            // complete, as another core must not resume it (or re-use its stack) until then.
+            thread.state = state;
+            thread.switching = true;
+
+            next
+        };
+
+        {
+            let mut thread = THREADS[next].lock();
+            thread.state = State::Running;
+            thread.core = core;
+            scheduler::set_running(thread.priority);
+        }
+
+        CURRENT.with(|current| current.set(Some(next)));
+        PREVIOUS.with(|cell| cell.set(Some(previous)));
+
+        // Safety: Interrupts are masked, and `next` was just removed from a run queue (or is the
+        // idle thread of this core), so no other core can resume it.
+        unsafe { Arch::switch_thread(SLOTS[previous].context.get(), SLOTS[next].context.get()) };
+
+        finish_switch();
+    });
+}
+
+/// Complete a switch once the previous thread of this core has been suspended, queueing it if it
+/// can run again or releasing its slot if it has exited.
+fn finish_switch() {
+    let Some(previous) = PREVIOUS.with(Cell::take) else {
+        return;
+    };
+
+    let mut thread = THREADS[previous].lock();
+    thread.switching = false;
+
+    match thread.state {
+        State::Ready => {
+            let core = thread.core;
+            thread.make_ready(previous, Some(core));
+        }
+        State::Exited => thread.state = State::Free,
+        State::Blocked => {}
+        State::Free | State::Running => unreachable!("switched away from an inactive thread"),
+    }
+}
+
+/// First code run by each new thread, once it is switched to for the first time.
+extern "C" fn thread_start(slot: usize) -> ! {
+    finish_switch();
+
+    let entry = THREADS[slot]
+        .lock()
+        .entry
+        .expect("new thread to have an entry point");
+
+    // Safety: Threads are only created once the board is initialised, so all interrupts can be
+    // serviced.
+    unsafe { Arch::enable_interrupts() };
+
+    entry();
+
+    exit();
+}
+
+/// Body of the idle thread of each core. Any thread that becomes ready will preempt it once the
+/// interrupt that readied it has been handled.
+fn idle() {
+    loop {
+        // Interrupts are masked between checking and waiting, so a reschedule requested in between
+        // still wakes the core. Without a tick, it might otherwise sleep indefinitely.
+        without_interrupts::<Arch, _, _>(|| {
+            if !scheduler::reschedule_pending() {
+                Arch::wait_for_interrupt();
+            }
+        });
+
+        scheduler::preempt();
+    }
+}
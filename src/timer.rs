@@ -0,0 +1,257 @@
+//! Software timers, multiplexed onto the hardware timer of each core.
+//!
+//! Each core keeps its timers in a fixed-size queue, with its hardware timer always armed for the
+//! earliest deadline, so a timer's callback runs on the core that started it. Callbacks are run
+//! from the IRQ handler, so they must be short and must not block.
+//!
+//! The hardware timer is the highest rated clock event device of the board which each core has its
+//! own instance of, and deadlines are measured against its counter.
+
+use core::time::Duration;
+
+use lib_kernel::{
+    sync::OnceCell,
+    time::{ClockEvent, TickConversion},
+    without_interrupts, Arch as _, Bsp as _,
+};
+
+use crate::{Arch, IrqSpinMutex, PerCpu, BSP};
+
+/// Maximum number of software timers that may be active at once.
+const MAX_TIMERS: usize = 32;
+
+/// Queue of active timers for each core.
+static QUEUES: PerCpu<IrqSpinMutex<TimerQueue>> =
+    PerCpu::new([const { IrqSpinMutex::new(TimerQueue::new()) }; Arch::MAX_CORES]);
+
+/// Clock event device that timers are multiplexed onto, with the conversion for its frequency.
+static DEVICE: OnceCell<Arch, (&'static dyn ClockEvent, TickConversion)> = OnceCell::new();
+
+/// Handle to an active timer, which can be used to cancel it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimerHandle {
+    /// Core whose queue holds the timer.
+    core: usize,
+    /// Slot in the queue that the timer occupies.
+    slot: usize,
+    /// Generation of the slot when the timer was created, so a stale handle cannot cancel a
+    /// different timer that later re-uses the slot.
+    generation: u64,
+}
+
+/// A single software timer.
+struct Timer {
+    /// Counter value at which the timer expires.
+    deadline: u64,
+    /// Number of counter ticks between expiries, if this timer repeats.
+    period: Option<u64>,
+    /// Function to run once the timer expires.
+    callback: Callback,
+}
+
+/// Function to run once a timer expires.
+#[derive(Clone, Copy)]
+enum Callback {
+    Plain(fn()),
+    /// Function which is passed an argument provided when the timer was started.
+    WithArgument(fn(usize), usize),
+}
+
+impl Callback {
+    fn call(self) {
+        match self {
+            Callback::Plain(callback) => callback(),
+            Callback::WithArgument(callback, argument) => callback(argument),
+        }
+    }
+}
+
+struct TimerQueue {
+    timers: [Option<Timer>; MAX_TIMERS],
+    generations: [u64; MAX_TIMERS],
+}
+
+impl TimerQueue {
+    const fn new() -> Self {
+        Self {
+            timers: [const { None }; MAX_TIMERS],
+            generations: [0; MAX_TIMERS],
+        }
+    }
+
+    /// Insert a new timer into the queue, returning `None` if the queue is full.
+    fn insert(&mut self, timer: Timer) -> Option<TimerHandle> {
+        let slot = self.timers.iter().position(Option::is_none)?;
+
+        self.timers[slot] = Some(timer);
+        self.generations[slot] += 1;
+
+        Some(TimerHandle {
+            core: Arch::core_id(),
+            slot,
+            generation: self.generations[slot],
+        })
+    }
+
+    /// Remove the timer referred to by `handle`, returning whether it was still active.
+    fn remove(&mut self, handle: TimerHandle) -> bool {
+        if self.generations[handle.slot] != handle.generation {
+            return false;
+        }
+
+        self.timers[handle.slot].take().is_some()
+    }
+
+    /// Slot of the timer with the earliest deadline.
+    fn earliest(&self) -> Option<usize> {
+        self.timers
+            .iter()
+            .enumerate()
+            .filter_map(|(slot, timer)| Some((slot, timer.as_ref()?.deadline)))
+            .min_by_key(|(_, deadline)| *deadline)
+            .map(|(slot, _)| slot)
+    }
+
+    /// Take the callback of the earliest timer if it has expired by `now`. Periodic timers are
+    /// re-queued for their next deadline, whilst one-shot timers are removed.
+    fn pop_expired(&mut self, now: u64) -> Option<Callback> {
+        let slot = self.earliest()?;
+        let timer = self.timers[slot].as_mut()?;
+
+        if timer.deadline > now {
+            return None;
+        }
+
+        let callback = timer.callback;
+
+        match timer.period {
+            Some(period) => {
+                timer.deadline += period;
+
+                // If the deadline was missed entirely, don't try to catch up on every expiry
+                if timer.deadline <= now {
+                    timer.deadline = now + period;
+                }
+            }
+            None => self.timers[slot] = None,
+        }
+
+        Some(callback)
+    }
+
+    /// Arm the hardware timer for the earliest deadline, or disarm it if the queue is empty.
+    fn program(&self) {
+        let device = device();
+
+        let Some(timer) = self.earliest().and_then(|slot| self.timers[slot].as_ref()) else {
+            device.cancel();
+            return;
+        };
+
+        // A deadline which has already passed may not raise an interrupt, so is moved later until
+        // the device accepts it
+        let mut deadline = timer.deadline;
+        while device.set_deadline(deadline).is_err() {
+            deadline = device.counter() + 1;
+        }
+    }
+}
+
+/// The device, and its conversion, choosing the device the first time it is needed.
+fn selected() -> &'static (&'static dyn ClockEvent, TickConversion) {
+    DEVICE.get_or_init(|| {
+        let device = BSP
+            .clock_events()
+            .filter(|event| event.per_core())
+            .max_by_key(|event| event.rating())
+            .expect("board to provide a per-core clock event device");
+
+        (device, TickConversion::new(device.frequency()))
+    })
+}
+
+/// Clock event device that timers are multiplexed onto.
+pub fn device() -> &'static dyn ClockEvent {
+    selected().0
+}
+
+/// Current value of the counter that timer deadlines are measured against.
+pub fn counter() -> u64 {
+    device().counter()
+}
+
+/// Run a closure with exclusive access to the queue of the current core.
+fn with_queue<T>(f: impl FnOnce(&mut TimerQueue) -> T) -> T {
+    QUEUES.with(|queue| f(&mut queue.lock()))
+}
+
+/// Convert a duration into a number of ticks of [`counter`], rounding down.
+pub fn duration_to_ticks(duration: Duration) -> u64 {
+    selected().1.duration_to_ticks(duration)
+}
+
+fn start(delay: Duration, period: Option<Duration>, callback: Callback) -> Option<TimerHandle> {
+    let timer = Timer {
+        deadline: counter() + duration_to_ticks(delay),
+        // A period of zero would never allow the queue to drain
+        period: period.map(|period| duration_to_ticks(period).max(1)),
+        callback,
+    };
+
+    with_queue(|queue| {
+        let handle = queue.insert(timer)?;
+        queue.program();
+
+        Some(handle)
+    })
+}
+
+/// Run `callback` on the current core once after `delay` has elapsed.
+///
+/// Returns `None` if the maximum number of timers are already active on this core.
+pub fn oneshot(delay: Duration, callback: fn()) -> Option<TimerHandle> {
+    start(delay, None, Callback::Plain(callback))
+}
+
+/// Run `callback(argument)` on the current core once after `delay` has elapsed.
+///
+/// Returns `None` if the maximum number of timers are already active on this core.
+pub fn oneshot_with(delay: Duration, callback: fn(usize), argument: usize) -> Option<TimerHandle> {
+    start(delay, None, Callback::WithArgument(callback, argument))
+}
+
+/// Run `callback` on the current core every `period`, starting one `period` from now.
+///
+/// Returns `None` if the maximum number of timers are already active on this core.
+pub fn periodic(period: Duration, callback: fn()) -> Option<TimerHandle> {
+    start(period, Some(period), Callback::Plain(callback))
+}
+
+/// Cancel an active timer, returning whether it was still active. The timer may have been
+/// started on any core.
+pub fn cancel(handle: TimerHandle) -> bool {
+    without_interrupts::<Arch, _, _>(|| {
+        let mut queue = QUEUES.get_for(handle.core).lock();
+        let removed = queue.remove(handle);
+
+        // The hardware timer of another core can't be re-armed from here, but an early expiry
+        // will re-arm it when it finds nothing to run.
+        if handle.core == Arch::core_id() {
+            queue.program();
+        }
+
+        removed
+    })
+}
+
+/// Run the callbacks of all expired timers, and re-arm the hardware timer for the next deadline.
+///
+/// Must be called in response to [`lib_kernel::Interrupt::ClockEvent`] from [`device`].
+pub fn handle_irq() {
+    // Callbacks are run without the lock held, so they may start or cancel timers themselves.
+    while let Some(callback) = with_queue(|queue| queue.pop_expired(counter())) {
+        callback.call();
+    }
+
+    with_queue(|queue| queue.program());
+}
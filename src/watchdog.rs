@@ -0,0 +1,51 @@
+//! Kicking of the board's hardware watchdog, so that the board is reset if the kernel stops
+//! scheduling threads.
+//!
+//! The watchdog is kicked by a high priority thread, so it is only starved if interrupts or the
+//! scheduler stop working, or if high priority threads never yield.
+
+use core::time::Duration;
+
+use lib_kernel::{sync::OnceCell, Bsp as _};
+use log::{info, warn};
+
+use crate::{scheduler::Priority, thread, Arch, BSP};
+
+/// Time without a kick after which the board is reset.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Time between each kick, based on the timeout that the board chose.
+static INTERVAL: OnceCell<Arch, Duration> = OnceCell::new();
+
+/// Start the hardware watchdog, and the thread which kicks it.
+///
+/// # Panics
+///
+/// Panics if the watchdog has already been started, or no more threads can be created.
+pub fn start() {
+    let Some(timeout) = BSP.start_watchdog(TIMEOUT) else {
+        warn!("Board has no watchdog");
+        return;
+    };
+
+    assert!(
+        INTERVAL.set(timeout / 4).is_ok(),
+        "watchdog started more than once"
+    );
+
+    thread::spawn(kick, Priority::High).expect("thread to be available for the watchdog");
+
+    info!("Watchdog started with a timeout of {timeout:?}");
+}
+
+/// Body of the thread which kicks the watchdog.
+fn kick() {
+    let interval = *INTERVAL
+        .get()
+        .expect("interval to be set before the thread starts");
+
+    loop {
+        BSP.kick_watchdog();
+        thread::sleep(interval);
+    }
+}
@@ -0,0 +1,180 @@
+//! Deferred work, allowing interrupt handlers to push anything non-urgent out of interrupt
+//! context.
+//!
+//! Each core has its own queue of [`Work`] items. Pending work is run at the end of the outermost
+//! IRQ handler with interrupts enabled, so long-running work never delays other interrupts.
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use lib_kernel::{ipi::Ipi, Arch as _, Bsp as _};
+
+use crate::{thread, Arch, IrqSpinMutex, PerCpu, BSP};
+
+/// Maximum number of work items that may be pending on a single core.
+const QUEUE_CAPACITY: usize = 64;
+
+/// Queue of pending work for each core.
+static QUEUES: PerCpu<IrqSpinMutex<WorkQueue>> =
+    PerCpu::new([const { IrqSpinMutex::new(WorkQueue::new()) }; Arch::MAX_CORES]);
+
+/// Whether each thread, indexed by [`thread::ThreadId::index`], is currently running pending
+/// work. The flag belongs to the thread rather than the core, as the work may block or be
+/// preempted, leaving other threads on the core to run any work queued in the meantime.
+static RUNNING: [AtomicBool; thread::MAX_THREADS] =
+    [const { AtomicBool::new(false) }; thread::MAX_THREADS];
+
+/// An item of work that can be deferred. A work item can only be queued once at a time, so
+/// scheduling it again whilst it is pending has no effect.
+pub struct Work {
+    /// Function to run.
+    func: fn(),
+    /// Whether this work is currently queued.
+    pending: AtomicBool,
+}
+
+impl Work {
+    /// Create a new work item, which will run `func`.
+    pub const fn new(func: fn()) -> Self {
+        Self {
+            func,
+            pending: AtomicBool::new(false),
+        }
+    }
+}
+
+struct WorkQueue {
+    items: [Option<&'static Work>; QUEUE_CAPACITY],
+    /// Index of the oldest item.
+    head: usize,
+    /// Number of items in the queue.
+    len: usize,
+}
+
+impl WorkQueue {
+    const fn new() -> Self {
+        Self {
+            items: [None; QUEUE_CAPACITY],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    /// Add an item to the back of the queue, returning `false` if the queue is full.
+    fn push(&mut self, work: &'static Work) -> bool {
+        if self.len == QUEUE_CAPACITY {
+            return false;
+        }
+
+        self.items[(self.head + self.len) % QUEUE_CAPACITY] = Some(work);
+        self.len += 1;
+
+        true
+    }
+
+    /// Determine whether the queue has no items.
+    fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Remove the item from the front of the queue.
+    fn pop(&mut self) -> Option<&'static Work> {
+        if self.len == 0 {
+            return None;
+        }
+
+        let work = self.items[self.head].take();
+        self.head = (self.head + 1) % QUEUE_CAPACITY;
+        self.len -= 1;
+
+        work
+    }
+}
+
+/// Queue `work` to run on the current core. Returns `false` if it was already pending.
+///
+/// # Panics
+///
+/// Panics if the queue of the current core is full.
+pub fn schedule(work: &'static Work) -> bool {
+    schedule_on(Arch::core_id(), work)
+}
+
+/// Queue `work` to run on `core`. Returns `false` if it was already pending.
+///
+/// If `core` isn't the current core, it is interrupted so that the work runs promptly.
+///
+/// # Panics
+///
+/// Panics if the queue of `core` is full.
+pub fn schedule_on(core: usize, work: &'static Work) -> bool {
+    if work.pending.swap(true, Ordering::AcqRel) {
+        return false;
+    }
+
+    let queued = QUEUES.get_for(core).lock().push(work);
+    assert!(queued, "work queue for core {core} is full");
+
+    if core != Arch::core_id() {
+        BSP.send_ipi(core, Ipi::Work);
+    }
+
+    true
+}
+
+/// Run all pending work for the current core until the queue is empty, including any work that
+/// is queued whilst running.
+pub fn flush() {
+    while let Some(work) = QUEUES.with(|queue| queue.lock().pop()) {
+        // Clear the flag first, so the work may re-schedule itself
+        work.pending.store(false, Ordering::Release);
+
+        (work.func)();
+    }
+}
+
+/// Run all pending work at the end of an IRQ handler. Interrupts are enabled whilst the work
+/// runs, and masked again before returning.
+///
+/// The work runs outside of interrupt context, so it may take any lock, block and be preempted.
+/// Interrupts nested within the work will not run it themselves, leaving it to the thread which
+/// is already doing so.
+pub fn run_pending_from_irq() {
+    let running = &RUNNING[thread::current().index()];
+    if running.swap(true, Ordering::Relaxed) {
+        return;
+    }
+
+    let core = Arch::core_id();
+
+    let depth = Arch::interrupt_depth();
+
+    // Safety: The depth is restored below, after IRQs are masked again.
+    unsafe { Arch::set_interrupt_depth(0) };
+
+    loop {
+        // Safety: The interrupt which caused this handler has been serviced, and the exception
+        // state of the interrupted code has been saved, so nested interrupts can be taken.
+        unsafe { Arch::enable_interrupts() };
+
+        flush();
+
+        Arch::disable_interrupts();
+
+        // An interrupt taken after the queue was emptied, but before IRQs were masked, will have
+        // left any work it queued to this handler
+        if QUEUES.with(|queue| queue.lock().is_empty()) {
+            break;
+        }
+    }
+
+    // Safety: IRQs are masked, and the handler has yet to return.
+    unsafe { Arch::set_interrupt_depth(depth) };
+
+    running.store(false, Ordering::Relaxed);
+
+    // If the work was preempted and resumed on another core, work queued on the original core in
+    // the meantime may have no interrupt left to run it, so the core must be interrupted
+    if core != Arch::core_id() && !QUEUES.get_for(core).lock().is_empty() {
+        BSP.send_ipi(core, Ipi::Work);
+    }
+}